@@ -0,0 +1,180 @@
+//! The optional config file. It currently holds custom TUI actions:
+//!
+//! ```toml
+//! [[action]]
+//! key = "f"
+//! name = "fetch"
+//! cmd = "git -C {worktree} fetch --all"
+//! background = true
+//! ```
+
+use crate::{template::Template, Repo};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    action: Vec<ActionEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActionEntry {
+    key: char,
+    cmd: String,
+    name: Option<String>,
+    #[serde(default)]
+    background: bool,
+}
+
+/// Keys the result browser already uses; actions may not rebind them.
+const RESERVED_KEYS: &str = "qjkgGais eoF/";
+
+/// A user-defined command bound to a key in the result browser.
+pub struct CustomAction {
+    pub key: char,
+    pub name: String,
+    /// Command line run through the shell, with placeholders quoted.
+    pub command: Template,
+    /// Run without suspending the TUI, reporting the exit status in the list.
+    pub background: bool,
+}
+
+impl CustomAction {
+    /// The shell command for `repo`, run from its work tree (or git dir when bare).
+    pub fn command_for(&self, repo: &Repo) -> Command {
+        let line = self.command.render_with(repo, shell_quote);
+        let mut command = shell_command(&line);
+        command.current_dir(repo.work_tree.as_deref().unwrap_or(&repo.path));
+        command
+    }
+}
+
+/// Reads actions from `path`, or from the default location when no path is
+/// given. A missing default config is not an error.
+pub fn load_actions(path: Option<&Path>) -> Result<Vec<CustomAction>> {
+    let (path, explicit) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(Vec::new()),
+        },
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if !explicit && err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("reading config {}", path.display())),
+    };
+    let file: ConfigFile =
+        toml::from_str(&text).with_context(|| format!("parsing config {}", path.display()))?;
+
+    let mut actions: Vec<CustomAction> = Vec::new();
+    for entry in file.action {
+        if RESERVED_KEYS.contains(entry.key) {
+            bail!("action key {:?} is already used by the TUI", entry.key);
+        }
+        if actions.iter().any(|a| a.key == entry.key) {
+            bail!("action key {:?} is bound more than once", entry.key);
+        }
+        let command = Template::parse(&entry.cmd)
+            .with_context(|| format!("invalid cmd for action key {:?}", entry.key))?;
+        let name = entry.name.unwrap_or_else(|| {
+            entry
+                .cmd
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        });
+        actions.push(CustomAction {
+            key: entry.key,
+            name,
+            command,
+            background: entry.background,
+        });
+    }
+    Ok(actions)
+}
+
+/// `$XDG_CONFIG_HOME/find-git-dirs/config.toml`, defaulting to `~/.config`;
+/// `%APPDATA%\find-git-dirs\config.toml` on Windows.
+fn default_path() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else {
+        match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        }
+    };
+    Some(base.join("find-git-dirs").join("config.toml"))
+}
+
+#[cfg(not(windows))]
+fn shell_command(line: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(line);
+    command
+}
+
+#[cfg(windows)]
+fn shell_command(line: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/C").arg(line);
+    command
+}
+
+/// Quotes a substituted value so the shell sees it as one word.
+#[cfg(not(windows))]
+fn shell_quote(value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "/._-+:@%,=".contains(c);
+    if !value.is_empty() && value.chars().all(safe) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(windows)]
+fn shell_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_plain_words_alone() {
+        assert_eq!(shell_quote("/srv/src/a-b_c.git"), "/srv/src/a-b_c.git");
+        assert_eq!(shell_quote("git@example.com:me/x"), "git@example.com:me/x");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn quoted_values_reach_the_command_intact() {
+        for value in [
+            "/tmp/a b",
+            "it's",
+            "$HOME `id` \"x\"",
+            "semi;colon",
+            "new\nline",
+            "*",
+        ] {
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf %s {}", shell_quote(value)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), value);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! The `exec` subcommand: runs a command in every repository as soon as the
+//! scan finds it.
+
+use crate::{prepare_scan, spawn_scanners, App, Msg, Scan, ScanArgs};
+use anyhow::Result;
+use crossbeam_channel::{bounded, never, select, unbounded};
+use std::{
+    ffi::OsString,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct ExecArgs {
+    /// How many commands run at once (default: the number of CPUs)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Print each repository's output as one block when its command finishes,
+    /// instead of prefixing every line with the repository path
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    group: bool,
+
+    /// Start no further commands once one has failed
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    fail_fast: bool,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// Root path(s) to scan
+    #[arg(value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    /// The command and its arguments, after `--`
+    #[arg(value_name = "CMD", last = true, required = true)]
+    command: Vec<OsString>,
+}
+
+/// Exit code for a command that could not be started, as in shells.
+const NOT_RUN: i32 = 127;
+
+/// Scans the roots and runs the command in each repository found, at most
+/// `--jobs` at a time. Returns the highest exit code of any command, counting
+/// codes below 1 from failed commands as 1, so the result is 0 only when every
+/// command succeeded.
+pub fn run(args: ExecArgs) -> Result<i32> {
+    let ExecArgs {
+        jobs,
+        group,
+        fail_fast,
+        scan,
+        paths,
+        command,
+    } = args;
+    let Some(Scan { roots, options, .. }) = prepare_scan(scan, paths)? else {
+        return Ok(0);
+    };
+
+    let (tx, rx) = bounded::<Msg>(1024);
+    spawn_scanners(&roots, &options, tx)?;
+    let mut app = App::new(roots, false);
+
+    let workers = jobs
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1);
+    let stop = Arc::new(AtomicBool::new(false));
+    let (job_tx, job_rx) = unbounded::<PathBuf>();
+    let (done_tx, done_rx) = unbounded::<(PathBuf, Option<io::Result<i32>>)>();
+    let command = Arc::new(command);
+    // Keeps a grouped block of stdout and stderr from interleaving with another
+    let output_lock = Arc::new(Mutex::new(()));
+    for _ in 0..workers.max(1) {
+        let job_rx = job_rx.clone();
+        let done_tx = done_tx.clone();
+        let stop = Arc::clone(&stop);
+        let command = Arc::clone(&command);
+        let output_lock = Arc::clone(&output_lock);
+        thread::spawn(move || {
+            while let Ok(dir) = job_rx.recv() {
+                // Jobs queued before a --fail-fast stop are skipped
+                let code = if stop.load(Ordering::SeqCst) {
+                    None
+                } else if group {
+                    Some(run_grouped(&command, &dir, &output_lock))
+                } else {
+                    Some(run_prefixed(&command, &dir))
+                };
+                if done_tx.send((dir, code)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let mut queued = 0usize;
+    let mut finished = 0usize;
+    let mut failed = 0usize;
+    let mut skipped = 0usize;
+    let mut exit_code = 0;
+    let mut scan_closed = false;
+    let idle = never();
+    loop {
+        let scanning = !app.all_done() && !scan_closed && !stop.load(Ordering::SeqCst);
+        if !scanning && finished == queued {
+            break;
+        }
+        let scan = if scanning { &rx } else { &idle };
+        select! {
+            recv(scan) -> msg => {
+                let Ok(msg) = msg else {
+                    scan_closed = true;
+                    continue;
+                };
+                if let Some(repo) = app.handle(msg) {
+                    // Bare repositories have no work tree, so use the git dir
+                    let dir = repo.work_tree.as_deref().unwrap_or(&repo.path);
+                    job_tx.send(dir.to_path_buf())?;
+                    queued += 1;
+                }
+            }
+            recv(done_rx) -> done => {
+                let (dir, code) = done?;
+                finished += 1;
+                match code {
+                    None => skipped += 1,
+                    Some(Ok(0)) => {}
+                    Some(result) => {
+                        failed += 1;
+                        let code = match result {
+                            Ok(code) => {
+                                eprintln!("find-git-dirs: {}: exited with {}", dir.display(), code);
+                                code
+                            }
+                            Err(err) => {
+                                eprintln!(
+                                    "find-git-dirs: {}: could not run command: {}",
+                                    dir.display(),
+                                    err
+                                );
+                                NOT_RUN
+                            }
+                        };
+                        // Windows crash codes are negative, yet still failures
+                        exit_code = exit_code.max(code.max(1));
+                        if fail_fast {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        eprintln!(
+            "find-git-dirs: command failed in {} of {} repositories",
+            failed,
+            finished - skipped
+        );
+    }
+    if stop.load(Ordering::SeqCst) {
+        eprintln!("find-git-dirs: stopped after the first failure (--fail-fast)");
+    }
+    Ok(exit_code)
+}
+
+fn command_in(command: &[OsString], dir: &Path) -> Command {
+    let mut cmd = Command::new(&command[0]);
+    cmd.args(&command[1..])
+        .current_dir(dir)
+        .stdin(Stdio::null());
+    cmd
+}
+
+/// The command's exit code; killed by a signal when there is none.
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    status.code().unwrap_or(1)
+}
+
+/// Runs the command with every line it prints prefixed by `dir`, keeping
+/// stdout and stderr apart.
+fn run_prefixed(command: &[OsString], dir: &Path) -> io::Result<i32> {
+    let mut child = command_in(command, dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let prefix = format!("{}: ", dir.display());
+    let stdout = child.stdout.take().map(|out| {
+        let prefix = prefix.clone();
+        thread::spawn(move || forward_lines(out, &prefix, io::stdout()))
+    });
+    let stderr = child
+        .stderr
+        .take()
+        .map(|err| thread::spawn(move || forward_lines(err, &prefix, io::stderr())));
+    for forwarder in stdout.into_iter().chain(stderr) {
+        let _ = forwarder.join();
+    }
+    Ok(exit_code(child.wait()?))
+}
+
+/// Copies `input` to `output` a line at a time, each written in one call so
+/// lines from parallel commands do not mix.
+fn forward_lines(input: impl Read, prefix: &str, output: impl Write) {
+    let mut reader = BufReader::new(input);
+    let mut output = output;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if line.last() != Some(&b'\n') {
+            line.push(b'\n');
+        }
+        let mut buf = Vec::with_capacity(prefix.len() + line.len());
+        buf.extend_from_slice(prefix.as_bytes());
+        buf.extend_from_slice(&line);
+        // A closed stdout (`| head`) just discards the rest
+        let _ = output.write_all(&buf);
+    }
+}
+
+/// Runs the command to completion and prints its output under a
+/// `==> dir <==` header; commands that print nothing get no header.
+fn run_grouped(command: &[OsString], dir: &Path, lock: &Mutex<()>) -> io::Result<i32> {
+    let output = command_in(command, dir).output()?;
+    if !output.stdout.is_empty() || !output.stderr.is_empty() {
+        let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut stdout = io::stdout().lock();
+        let _ = writeln!(stdout, "==> {} <==", dir.display());
+        let _ = stdout.write_all(&output.stdout);
+        let _ = stdout.flush();
+        let _ = io::stderr().write_all(&output.stderr);
+    }
+    Ok(exit_code(output.status))
+}
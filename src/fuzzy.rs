@@ -0,0 +1,138 @@
+//! Subsequence fuzzy matching for the TUI filter, in the spirit of fzf's
+//! first algorithm: find the shortest window holding the pattern, then score
+//! it, favouring consecutive characters and matches at word boundaries.
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 8;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+#[derive(Clone, Debug)]
+pub struct Match {
+    pub score: i64,
+    /// Character (not byte) indices of the matched characters in the text.
+    pub positions: Vec<usize>,
+}
+
+/// Matches `pattern` against `text`. Matching ignores case unless the
+/// pattern contains an uppercase letter; an empty pattern matches anything.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    if pattern.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    let case_sensitive = pattern.iter().any(|c| c.is_uppercase());
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let text: Vec<char> = text.chars().collect();
+
+    // Forward pass: the earliest point where the whole pattern has been seen
+    let mut pi = 0;
+    let mut end = None;
+    for (i, &c) in text.iter().enumerate() {
+        if fold(c) == pattern[pi] {
+            pi += 1;
+            if pi == pattern.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    // Backward pass: the latest start that still fits, giving the tightest window
+    let mut pi = pattern.len();
+    let mut start = end;
+    for i in (0..=end).rev() {
+        if fold(text[i]) == pattern[pi - 1] {
+            pi -= 1;
+            if pi == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut score = 0;
+    let mut pi = 0;
+    let mut gap = 0;
+    for (i, &c) in text.iter().enumerate().take(end + 1).skip(start) {
+        if pi < pattern.len() && fold(c) == pattern[pi] {
+            score += SCORE_MATCH;
+            if i == 0 || is_boundary(text[i - 1]) {
+                score += BONUS_BOUNDARY;
+            }
+            if positions.last() == Some(&(i.wrapping_sub(1))) {
+                score += BONUS_CONSECUTIVE;
+            }
+            positions.push(i);
+            pi += 1;
+            gap = 0;
+        } else {
+            score -= if gap == 0 {
+                PENALTY_GAP_START
+            } else {
+                PENALTY_GAP_EXTENSION
+            };
+            gap += 1;
+        }
+    }
+
+    Some(Match { score, positions })
+}
+
+fn is_boundary(c: char) -> bool {
+    matches!(c, '/' | '\\' | '-' | '_' | '.' | ' ' | ':' | '@')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(pattern: &str, text: &str) -> Option<Vec<usize>> {
+        fuzzy_match(pattern, text).map(|m| m.positions)
+    }
+
+    #[test]
+    fn matches_subsequences() {
+        assert_eq!(positions("fgd", "find-git-dirs"), Some(vec![0, 5, 9]));
+        assert_eq!(positions("", "anything"), Some(vec![]));
+        assert_eq!(positions("xyz", "find-git-dirs"), None);
+        assert_eq!(positions("sd", "ds"), None);
+    }
+
+    #[test]
+    fn uses_the_tightest_window() {
+        // `ab` matches the adjacent pair, not the first `a` and a distant `b`
+        assert_eq!(positions("ab", "a---ab"), Some(vec![4, 5]));
+    }
+
+    #[test]
+    fn uppercase_makes_matching_case_sensitive() {
+        assert!(fuzzy_match("src", "/home/me/Src").is_some());
+        assert!(fuzzy_match("Src", "/home/me/src").is_none());
+        assert!(fuzzy_match("Src", "/home/me/Src").is_some());
+    }
+
+    #[test]
+    fn positions_count_characters_not_bytes() {
+        assert_eq!(positions("ü", "/tmp/müll"), Some(vec![6]));
+    }
+
+    #[test]
+    fn prefers_consecutive_and_boundary_matches() {
+        let score = |pattern, text| fuzzy_match(pattern, text).unwrap().score;
+        assert!(score("git", "/src/git") > score("git", "/src/gxixt"));
+        assert!(score("git", "/src/git") > score("git", "/src/digit"));
+    }
+}
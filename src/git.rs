@@ -0,0 +1,891 @@
+use crate::odb::{self, ObjectStore};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::UNIX_EPOCH,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepoKind {
+    Repo,
+    Worktree,
+    Submodule,
+    Bare,
+}
+
+impl RepoKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RepoKind::Repo => "repo",
+            RepoKind::Worktree => "worktree",
+            RepoKind::Submodule => "submodule",
+            RepoKind::Bare => "bare",
+        }
+    }
+}
+
+/// A `.git` file resolved to the git directory it points at.
+pub struct LinkedGitDir {
+    pub git_dir: PathBuf,
+    pub kind: RepoKind,
+    /// Git directory of the repository this one belongs to, if any.
+    pub main: Option<PathBuf>,
+}
+
+/// Parses a `.git` file of the form `gitdir: <path>` and classifies the
+/// target as a linked worktree, a submodule or a plain separated git dir.
+pub fn resolve_git_file(file: &Path) -> Option<LinkedGitDir> {
+    let contents = fs::read_to_string(file).ok()?;
+    let target = contents
+        .lines()
+        .find_map(|l| l.strip_prefix("gitdir:"))
+        .map(str::trim)
+        .filter(|t| !t.is_empty())?;
+
+    let base = file.parent().unwrap_or_else(|| Path::new("."));
+    let git_dir = canonical_or_self(&base.join(target));
+    if !git_dir.is_dir() {
+        return None;
+    }
+
+    // Linked worktrees carry a `commondir` file pointing back at the main
+    // repository's git dir; submodules live under `<super>/modules/<name>`.
+    if let Ok(common) = fs::read_to_string(git_dir.join("commondir")) {
+        let main = canonical_or_self(&git_dir.join(common.trim()));
+        return Some(LinkedGitDir {
+            git_dir,
+            kind: RepoKind::Worktree,
+            main: Some(main),
+        });
+    }
+
+    if let Some(main) = superproject_git_dir(&git_dir) {
+        return Some(LinkedGitDir {
+            git_dir,
+            kind: RepoKind::Submodule,
+            main: Some(main),
+        });
+    }
+
+    Some(LinkedGitDir {
+        git_dir,
+        kind: RepoKind::Repo,
+        main: None,
+    })
+}
+
+/// Finds the nearest `<dir>/modules/...` ancestor where `<dir>` is itself a
+/// git directory, which is how git lays out absorbed submodules.
+fn superproject_git_dir(git_dir: &Path) -> Option<PathBuf> {
+    git_dir
+        .ancestors()
+        .skip(1)
+        .filter(|a| a.file_name() == Some(OsStr::new("modules")))
+        .filter_map(Path::parent)
+        .find(|p| p.join("HEAD").is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Branch, HEAD and remote information read straight from a git dir.
+#[derive(Clone, Debug, Default)]
+pub struct Details {
+    /// Short name of the checked-out branch; `None` when HEAD is detached.
+    pub branch: Option<String>,
+    /// Commit HEAD points at; `None` for an unborn branch.
+    pub head: Option<String>,
+    /// Committer time of HEAD in seconds since the Unix epoch.
+    pub last_commit: Option<i64>,
+    pub remotes: Vec<Remote>,
+    pub branches: Vec<Branch>,
+}
+
+impl Details {
+    /// Whether any local branch has commits its upstream does not.
+    pub fn has_unpushed(&self) -> bool {
+        self.branches
+            .iter()
+            .any(|b| b.ahead_behind.is_some_and(|(ahead, _)| ahead > 0))
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Remote {
+    pub name: String,
+    pub url: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Branch {
+    pub name: String,
+    /// Short name of the configured upstream, such as `origin/main`.
+    pub upstream: Option<String>,
+    /// Commits only on the branch and only on its upstream. `None` when there
+    /// is no upstream, its ref is gone, or the history could not be read.
+    pub ahead_behind: Option<(usize, usize)>,
+}
+
+/// Reads `HEAD`, `config` and `packed-refs` without spawning git. Worktrees
+/// keep their own `HEAD` but share refs and config with the common dir.
+pub fn read_details(git_dir: &Path) -> Details {
+    let common = common_dir(git_dir);
+    let mut details = Details::default();
+
+    if let Ok(head) = fs::read_to_string(git_dir.join("HEAD")) {
+        let head = head.trim();
+        match head.strip_prefix("ref:") {
+            Some(target) => {
+                let target = target.trim();
+                details.branch = Some(
+                    target
+                        .strip_prefix("refs/heads/")
+                        .unwrap_or(target)
+                        .to_string(),
+                );
+                details.head = resolve_ref(git_dir, &common, target);
+            }
+            None if is_object_id(head) => details.head = Some(head.to_string()),
+            None => {}
+        }
+    }
+
+    let config = Config::read(&common.join("config")).unwrap_or_default();
+    details.remotes = config
+        .subsections("remote")
+        .into_iter()
+        .map(|name| {
+            // The first url is the one git fetches from
+            let url = config
+                .get_all("remote", Some(&name), "url")
+                .first()
+                .map(|u| u.to_string());
+            Remote { name, url }
+        })
+        .collect();
+    let mut store = None;
+    details.last_commit = details
+        .head
+        .as_deref()
+        .and_then(odb::parse_oid)
+        .and_then(|oid| {
+            store
+                .get_or_insert_with(|| ObjectStore::open(&common))
+                .read_commit(&oid)
+        })
+        .map(|c| c.commit_time);
+    details.branches = read_branches(git_dir, &common, &config, &mut store);
+
+    details
+}
+
+/// Lists local branches with their upstream and ahead/behind counts. The
+/// object store is only opened if some branch actually tracks something.
+fn read_branches(
+    git_dir: &Path,
+    common: &Path,
+    config: &Config,
+    store: &mut Option<ObjectStore>,
+) -> Vec<Branch> {
+    local_branches(common)
+        .into_iter()
+        .map(|(name, id)| {
+            let Some((upstream, upstream_ref)) = upstream_of(config, &name) else {
+                return Branch {
+                    name,
+                    upstream: None,
+                    ahead_behind: None,
+                };
+            };
+            let ahead_behind = resolve_ref(git_dir, common, &upstream_ref)
+                .and_then(|up| Some((odb::parse_oid(&id)?, odb::parse_oid(&up)?)))
+                .and_then(|(local, up)| {
+                    store
+                        .get_or_insert_with(|| ObjectStore::open(common))
+                        .ahead_behind(&local, &up)
+                });
+            Branch {
+                name,
+                upstream: Some(upstream),
+                ahead_behind,
+            }
+        })
+        .collect()
+}
+
+/// Branch names mapped to the commit they point at; loose refs under
+/// `refs/heads` take precedence over `packed-refs`.
+fn local_branches(common: &Path) -> BTreeMap<String, String> {
+    let mut branches: BTreeMap<String, String> = packed_refs(common)
+        .into_iter()
+        .filter_map(|(id, name)| Some((name.strip_prefix("refs/heads/")?.to_string(), id)))
+        .collect();
+
+    let heads = common.join("refs").join("heads");
+    let mut stack = vec![heads.clone()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+                continue;
+            }
+            let Ok(contents) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let id = contents.trim();
+            if !is_object_id(id) {
+                continue;
+            }
+            if let Some(name) = path.strip_prefix(&heads).ok().and_then(Path::to_str) {
+                branches.insert(name.replace('\\', "/"), id.to_string());
+            }
+        }
+    }
+
+    branches
+}
+
+/// Works out the upstream of `branch` from `branch.<name>.remote` and
+/// `branch.<name>.merge`, mapping the merge ref through the remote's fetch
+/// refspecs. Returns the short display name and the full ref name.
+fn upstream_of(config: &Config, branch: &str) -> Option<(String, String)> {
+    let remote = config.get("branch", Some(branch), "remote")?;
+    let merge = config.get("branch", Some(branch), "merge")?;
+    if remote == "." {
+        let short = merge.strip_prefix("refs/heads/").unwrap_or(merge);
+        return Some((short.to_string(), merge.to_string()));
+    }
+
+    let mapped = config
+        .get_all("remote", Some(remote), "fetch")
+        .into_iter()
+        .find_map(|spec| map_refspec(spec, merge))
+        .unwrap_or_else(|| {
+            let short = merge.strip_prefix("refs/heads/").unwrap_or(merge);
+            format!("refs/remotes/{}/{}", remote, short)
+        });
+    let short = mapped
+        .strip_prefix("refs/remotes/")
+        .or_else(|| mapped.strip_prefix("refs/heads/"))
+        .unwrap_or(&mapped)
+        .to_string();
+    Some((short, mapped))
+}
+
+/// Applies a fetch refspec such as `+refs/heads/*:refs/remotes/origin/*`.
+fn map_refspec(spec: &str, src_ref: &str) -> Option<String> {
+    let (src, dst) = spec.trim_start_matches('+').split_once(':')?;
+    match (src.split_once('*'), dst.split_once('*')) {
+        (Some((src_pre, src_post)), Some((dst_pre, dst_post))) => {
+            let middle = src_ref.strip_prefix(src_pre)?.strip_suffix(src_post)?;
+            Some(format!("{}{}{}", dst_pre, middle, dst_post))
+        }
+        (None, None) if src == src_ref => Some(dst.to_string()),
+        _ => None,
+    }
+}
+
+/// The directory holding shared refs, objects and config: the target of
+/// `commondir` for linked worktrees, otherwise the git dir itself.
+pub fn common_dir(git_dir: &Path) -> PathBuf {
+    match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => canonical_or_self(&git_dir.join(common.trim())),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+/// Resolves a ref name to an object id, following symbolic refs and falling
+/// back to `packed-refs`.
+pub fn resolve_ref(git_dir: &Path, common: &Path, name: &str) -> Option<String> {
+    let mut name = name.to_string();
+    // Symbolic ref chains are short in practice; the limit guards against loops
+    for _ in 0..8 {
+        let base = if name.starts_with("refs/") {
+            common
+        } else {
+            git_dir
+        };
+        match fs::read_to_string(base.join(&name)) {
+            Ok(contents) => {
+                let contents = contents.trim();
+                match contents.strip_prefix("ref:") {
+                    Some(target) => name = target.trim().to_string(),
+                    None if is_object_id(contents) => return Some(contents.to_string()),
+                    None => return None,
+                }
+            }
+            Err(_) => {
+                return packed_refs(common)
+                    .into_iter()
+                    .find_map(|(id, r)| (r == name).then_some(id))
+            }
+        }
+    }
+    None
+}
+
+/// Parses `packed-refs` into `(object id, ref name)` pairs, skipping the
+/// peeled `^<id>` lines that follow annotated tags.
+pub fn packed_refs(common: &Path) -> Vec<(String, String)> {
+    let Ok(contents) = fs::read_to_string(common.join("packed-refs")) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter(|l| !l.starts_with('#') && !l.starts_with('^'))
+        .filter_map(|l| l.split_once(' '))
+        .filter(|(id, _)| is_object_id(id))
+        .map(|(id, name)| (id.to_string(), name.trim().to_string()))
+        .collect()
+}
+
+fn is_object_id(s: &str) -> bool {
+    (s.len() == 40 || s.len() == 64) && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Reads the commit `id` names from the repository's object database.
+pub fn read_commit(git_dir: &Path, id: &str) -> Option<odb::Commit> {
+    let oid = odb::parse_oid(id)?;
+    ObjectStore::open(&common_dir(git_dir)).read_commit(&oid)
+}
+
+/// When the repository was last used, in seconds since the Unix epoch: the
+/// newest of HEAD's committer date and the modification times of `index`,
+/// `HEAD` and `FETCH_HEAD`, which checkouts, commits and fetches touch.
+pub fn last_activity(git_dir: &Path) -> Option<i64> {
+    let common = common_dir(git_dir);
+    let commit = resolve_ref(git_dir, &common, "HEAD")
+        .and_then(|id| read_commit(git_dir, &id))
+        .map(|c| c.commit_time);
+    let touched = [
+        git_dir.join("index"),
+        git_dir.join("HEAD"),
+        common.join("FETCH_HEAD"),
+    ]
+    .into_iter()
+    .filter_map(|path| modified_secs(&path));
+    commit.into_iter().chain(touched).max()
+}
+
+fn modified_secs(path: &Path) -> Option<i64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let secs = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    i64::try_from(secs).ok()
+}
+
+/// Number of entries on the stash, counted from the `refs/stash` reflog.
+pub fn stash_count(git_dir: &Path) -> usize {
+    let common = common_dir(git_dir);
+    match fs::read_to_string(common.join("logs/refs/stash")) {
+        Ok(log) => log.lines().filter(|l| !l.trim().is_empty()).count(),
+        // A stash without a reflog still has its newest entry
+        Err(_) => usize::from(resolve_ref(git_dir, &common, "refs/stash").is_some()),
+    }
+}
+
+/// Total size in bytes of the files under `dir`, without following symlinks.
+pub fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(ft) if ft.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Working tree change counts as reported by `git status`.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct Status {
+    pub staged: u32,
+    pub modified: u32,
+    pub untracked: u32,
+}
+
+impl Status {
+    pub fn is_clean(&self) -> bool {
+        self.staged == 0 && self.modified == 0 && self.untracked == 0
+    }
+}
+
+/// Runs `git status --porcelain=v2` in `work_tree` and tallies the entries.
+/// Returns `None` if git is missing or refuses to run (for example on a
+/// repository owned by another user).
+pub fn read_status(work_tree: &Path) -> Option<Status> {
+    let output = Command::new("git")
+        .arg("--no-optional-locks")
+        .arg("-C")
+        .arg(work_tree)
+        .args(["status", "--porcelain=v2", "--untracked-files=normal"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let mut status = Status::default();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let mut fields = line.split(' ');
+        match fields.next() {
+            Some("1") | Some("2") => {
+                let xy = fields.next().unwrap_or("..").as_bytes();
+                if xy.first().is_some_and(|&x| x != b'.') {
+                    status.staged += 1;
+                }
+                if xy.get(1).is_some_and(|&y| y != b'.') {
+                    status.modified += 1;
+                }
+            }
+            // Unmerged paths still need attention in the working tree
+            Some("u") => status.modified += 1,
+            Some("?") => status.untracked += 1,
+            _ => {}
+        }
+    }
+    Some(status)
+}
+
+/// Work tree of the repository whose `.git` entry is `dot_git`, honouring
+/// `core.bare` and `core.worktree`. `None` for bare repositories.
+pub fn work_tree(dot_git: &Path, git_dir: &Path, kind: RepoKind) -> Option<PathBuf> {
+    if kind == RepoKind::Worktree {
+        // Linked worktrees ignore core.worktree; their `.git` file sits at the root
+        return dot_git.parent().map(Path::to_path_buf);
+    }
+    let config = Config::read(&git_dir.join("config")).unwrap_or_default();
+    if config.get_bool("core", None, "bare") == Some(true) {
+        return None;
+    }
+    if let Some(dir) = config.get("core", None, "worktree") {
+        // Relative values are relative to the git dir, as in submodules
+        return Some(canonical_or_self(&git_dir.join(dir)));
+    }
+    match kind {
+        RepoKind::Bare => None,
+        _ => dot_git.parent().map(Path::to_path_buf),
+    }
+}
+
+/// How `git fetch` went for one repository.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FetchOutcome {
+    Ok,
+    /// The last lines git printed to stderr, or why it could not run.
+    Failed {
+        error: String,
+    },
+}
+
+/// Lines of stderr kept from a failed fetch.
+const FETCH_ERROR_LINES: usize = 5;
+
+/// Runs `git fetch --all --prune` against `git_dir`. Git's own credential
+/// prompts are disabled, and so are ssh's unless the user chose their own ssh
+/// command, so a remote that needs a password fails instead of hanging.
+pub fn fetch(git_dir: &Path) -> FetchOutcome {
+    let mut command = Command::new("git");
+    command
+        .arg("--git-dir")
+        .arg(git_dir)
+        .args(["fetch", "--all", "--prune", "--quiet"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null());
+    if !has_custom_ssh(git_dir) {
+        // ssh asks for passphrases and host keys on /dev/tty, which would draw
+        // over the TUI and wait forever
+        command.env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
+    }
+    let output = command.output();
+    let output = match output {
+        Ok(output) if output.status.success() => return FetchOutcome::Ok,
+        Ok(output) => output,
+        Err(err) => {
+            return FetchOutcome::Failed {
+                error: format!("could not run git: {}", err),
+            }
+        }
+    };
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    let tail = &lines[lines.len().saturating_sub(FETCH_ERROR_LINES)..];
+    FetchOutcome::Failed {
+        error: if tail.is_empty() {
+            output.status.to_string()
+        } else {
+            tail.join("\n")
+        },
+    }
+}
+
+/// Whether ssh is configured through `GIT_SSH_COMMAND`, `GIT_SSH` or
+/// `core.sshCommand`, which setting `GIT_SSH_COMMAND` would override.
+fn has_custom_ssh(git_dir: &Path) -> bool {
+    if ["GIT_SSH_COMMAND", "GIT_SSH"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some_and(|v| !v.is_empty()))
+    {
+        return true;
+    }
+    // Asking git covers the global config and includes too
+    Command::new("git")
+        .arg("--git-dir")
+        .arg(git_dir)
+        .args(["config", "--get", "core.sshCommand"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|o| o.status.success() && !o.stdout.trim_ascii().is_empty())
+}
+
+/// Recognizes a bare repository by its layout: a `HEAD` file next to
+/// `objects/` and `refs/`. Git dirs that explicitly set `core.bare = false`
+/// (such as absorbed submodules under `.git/modules`) are rejected.
+pub fn is_bare_repo(dir: &Path) -> bool {
+    if !looks_like_git_dir(dir) {
+        return false;
+    }
+    match Config::read(&dir.join("config")) {
+        Some(config) => config.get_bool("core", None, "bare") != Some(false),
+        None => true,
+    }
+}
+
+/// A git dir that is not named `.git` but points at its checkout through
+/// `core.worktree`, as used for dotfiles kept in `~/.dotfiles.git`.
+pub fn has_detached_work_tree(dir: &Path) -> bool {
+    if !looks_like_git_dir(dir) {
+        return false;
+    }
+    Config::read(&dir.join("config")).is_some_and(|config| {
+        config.get_bool("core", None, "bare") == Some(false)
+            && config.get("core", None, "worktree").is_some()
+    })
+}
+
+fn looks_like_git_dir(dir: &Path) -> bool {
+    dir.join("HEAD").is_file() && dir.join("objects").is_dir() && dir.join("refs").is_dir()
+}
+
+/// A parsed git config file. Section and key names are case-insensitive,
+/// subsection names are not; when a key repeats, the last value wins.
+#[derive(Default)]
+pub struct Config {
+    entries: Vec<ConfigEntry>,
+}
+
+struct ConfigEntry {
+    section: String,
+    subsection: Option<String>,
+    key: String,
+    value: String,
+}
+
+impl Config {
+    pub fn read(path: &Path) -> Option<Self> {
+        fs::read_to_string(path).ok().map(|s| Self::parse(&s))
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut entries = Vec::new();
+        let mut section = String::new();
+        let mut subsection: Option<String> = None;
+        let mut lines = text.lines();
+
+        while let Some(raw) = lines.next() {
+            let line = raw.trim_start();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix('[') {
+                let Some(end) = rest.find(']') else {
+                    continue;
+                };
+                let header = &rest[..end];
+                match header.find(|c: char| c.is_whitespace()) {
+                    Some(split) => {
+                        section = header[..split].to_ascii_lowercase();
+                        let sub = header[split..].trim();
+                        let sub = sub.strip_prefix('"').unwrap_or(sub);
+                        let sub = sub.strip_suffix('"').unwrap_or(sub);
+                        subsection = Some(sub.replace("\\\"", "\"").replace("\\\\", "\\"));
+                    }
+                    None => match header.split_once('.') {
+                        // Legacy `[section.subsection]` form, lowercased by git
+                        Some((sec, sub)) => {
+                            section = sec.to_ascii_lowercase();
+                            subsection = Some(sub.to_ascii_lowercase());
+                        }
+                        None => {
+                            section = header.to_ascii_lowercase();
+                            subsection = None;
+                        }
+                    },
+                }
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((k, v)) => {
+                    let mut value = v.to_string();
+                    // A trailing backslash continues the value on the next line
+                    while value.trim_end().ends_with('\\') && !value.trim_end().ends_with("\\\\") {
+                        let trimmed = value.trim_end();
+                        value = trimmed[..trimmed.len() - 1].to_string();
+                        match lines.next() {
+                            Some(next) => value.push_str(next),
+                            None => break,
+                        }
+                    }
+                    (k.trim(), parse_value(&value))
+                }
+                // A bare key is shorthand for `key = true`
+                None => (
+                    line.split(['#', ';']).next().unwrap_or("").trim(),
+                    "true".to_string(),
+                ),
+            };
+            if key.is_empty() || section.is_empty() {
+                continue;
+            }
+            entries.push(ConfigEntry {
+                section: section.clone(),
+                subsection: subsection.clone(),
+                key: key.to_ascii_lowercase(),
+                value,
+            });
+        }
+
+        Self { entries }
+    }
+
+    pub fn get(&self, section: &str, subsection: Option<&str>, key: &str) -> Option<&str> {
+        self.get_all(section, subsection, key).pop()
+    }
+
+    pub fn get_all(&self, section: &str, subsection: Option<&str>, key: &str) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|e| {
+                e.section.eq_ignore_ascii_case(section)
+                    && e.subsection.as_deref() == subsection
+                    && e.key.eq_ignore_ascii_case(key)
+            })
+            .map(|e| e.value.as_str())
+            .collect()
+    }
+
+    /// Distinct subsection names of `section`, in order of first appearance.
+    pub fn subsections(&self, section: &str) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for e in &self.entries {
+            if let Some(sub) = &e.subsection {
+                if e.section.eq_ignore_ascii_case(section) && !names.contains(sub) {
+                    names.push(sub.clone());
+                }
+            }
+        }
+        names
+    }
+
+    pub fn get_bool(&self, section: &str, subsection: Option<&str>, key: &str) -> Option<bool> {
+        match self
+            .get(section, subsection, key)?
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "true" | "yes" | "on" | "1" => Some(true),
+            "false" | "no" | "off" | "0" | "" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Unquotes a config value, handling escapes and stripping trailing comments.
+fn parse_value(raw: &str) -> String {
+    let mut out = String::new();
+    let mut in_quotes = false;
+    let mut pending_space = String::new();
+    let mut chars = raw.trim().chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '#' | ';' if !in_quotes => break,
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('b') => '\u{8}',
+                    Some(other) => other,
+                    None => break,
+                };
+                out.push_str(&pending_space);
+                pending_space.clear();
+                out.push(escaped);
+            }
+            c if c.is_whitespace() && !in_quotes => pending_space.push(c),
+            c => {
+                if !out.is_empty() {
+                    out.push_str(&pending_space);
+                }
+                pending_space.clear();
+                out.push(c);
+            }
+        }
+    }
+
+    out
+}
+
+fn canonical_or_self(p: &Path) -> PathBuf {
+    fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TestRepo;
+
+    const CONFIG: &str = r#"# comment
+[core]
+	bare = false
+	WorkTree = /srv/site
+[remote "origin"]
+	url = git@example.com:me/repo.git
+	fetch = +refs/heads/*:refs/remotes/origin/*
+[remote "Up Stream"]
+	url = "https://example.com/a b.git" ; trailing comment
+[branch.Legacy]
+	remote = origin
+[Core]
+	fileMode = off
+[alias]
+	lg = log --graph \
+--oneline
+	say = "!echo \"hi\"\tthere"
+[user]
+	useConfigOnly
+[core]
+	bare = yes
+"#;
+
+    #[test]
+    fn config_values_match_git() {
+        let config = Config::parse(CONFIG);
+        let repo = TestRepo::new();
+        let file = repo.path().join("sample.config");
+        fs::write(&file, CONFIG).unwrap();
+        let file = file.to_str().unwrap();
+        for (section, subsection, key) in [
+            ("core", None, "bare"),
+            ("core", None, "worktree"),
+            ("remote", Some("origin"), "url"),
+            ("remote", Some("origin"), "fetch"),
+            ("remote", Some("Up Stream"), "url"),
+            ("branch", Some("legacy"), "remote"),
+            ("core", None, "filemode"),
+            ("alias", None, "lg"),
+            ("alias", None, "say"),
+        ] {
+            let name = match subsection {
+                Some(sub) => format!("{}.{}.{}", section, sub, key),
+                None => format!("{}.{}", section, key),
+            };
+            let expected = repo.git(&["config", "--file", file, "--get", &name]);
+            assert_eq!(
+                config.get(section, subsection, key),
+                Some(&*expected),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn config_lookups() {
+        let config = Config::parse(CONFIG);
+        // Section and key names ignore case, subsection names do not
+        assert_eq!(config.get("CORE", None, "WORKTREE"), Some("/srv/site"));
+        assert_eq!(config.get("remote", Some("up stream"), "url"), None);
+        assert_eq!(config.get_all("core", None, "bare"), ["false", "yes"]);
+        assert_eq!(config.get_bool("core", None, "bare"), Some(true));
+        assert_eq!(config.get_bool("core", None, "filemode"), Some(false));
+        assert_eq!(config.get_bool("user", None, "useconfigonly"), Some(true));
+        assert_eq!(config.get_bool("alias", None, "lg"), None);
+        assert_eq!(config.subsections("remote"), ["origin", "Up Stream"]);
+    }
+
+    #[test]
+    fn respects_a_configured_ssh_command() {
+        let repo = TestRepo::new();
+        repo.git(&["config", "core.sshCommand", "ssh -i ~/.ssh/deploy"]);
+        assert!(has_custom_ssh(&repo.git_dir()));
+    }
+
+    #[test]
+    fn packed_refs_match_git() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "one\n");
+        repo.git(&["tag", "-a", "-m", "Release", "v1"]);
+        repo.git(&["tag", "light"]);
+        repo.git(&["branch", "feature"]);
+        repo.commit("a.txt", "two\n");
+        repo.git(&["pack-refs", "--all"]);
+
+        let git_dir = repo.git_dir();
+        let mut refs = packed_refs(&git_dir);
+        refs.sort();
+        let mut expected: Vec<(String, String)> = repo
+            .git(&["for-each-ref", "--format=%(objectname) %(refname)"])
+            .lines()
+            .map(|l| l.split_once(' ').unwrap())
+            .map(|(id, name)| (id.to_string(), name.to_string()))
+            .collect();
+        expected.sort();
+        assert_eq!(refs, expected);
+        // The annotated tag's peeled `^<id>` line is not a ref of its own
+        assert_eq!(
+            packed_refs(&git_dir)
+                .iter()
+                .filter(|(_, r)| r == "refs/tags/v1")
+                .count(),
+            1
+        );
+
+        // A loose ref written after packing takes precedence
+        repo.commit("a.txt", "three\n");
+        for name in [
+            "HEAD",
+            "refs/heads/main",
+            "refs/heads/feature",
+            "refs/tags/v1",
+        ] {
+            assert_eq!(
+                resolve_ref(&git_dir, &git_dir, name),
+                Some(repo.git(&["rev-parse", name])),
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -1,41 +1,91 @@
-use anyhow::Result;
-use clap::Parser;
-use crossbeam_channel::{bounded, select, tick, Sender};
+mod config;
+mod exec;
+mod fuzzy;
+mod git;
+mod odb;
+mod output;
+mod template;
+#[cfg(test)]
+mod test_repo;
+
+use anyhow::{Context, Result};
+use clap::{error::ErrorKind, parser::ValueSource, CommandFactory, FromArgMatches, Parser};
+use crossbeam_channel::{bounded, never, select, tick, unbounded, Receiver, Sender};
 use crossterm::{
     event::{self, Event, KeyCode},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ignore::{DirEntry, WalkBuilder, WalkState};
+use git::RepoKind;
+use ignore::{
+    overrides::{Override, OverrideBuilder},
+    WalkBuilder, WalkState,
+};
+use output::{emit_results, Column, LiveOutput, OutputFormat, OutputSpec, PrintField};
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, List, ListItem, Paragraph, Row, Table, Wrap},
+    widgets::{Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, Wrap},
 };
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    env,
     ffi::OsStr,
-    fs,
-    io::{self, stdout, Write},
+    fs::{self, FileType},
+    io::{self, IsTerminal, Write},
     path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
     sync::{Arc, Mutex},
     thread,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
+use template::Template;
 
 #[derive(Parser, Debug)]
 #[command(about = "TUI scanner for all .git directories")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+
     /// Output JSON after exit (default)
-    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "plain")]
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["plain", "format"])]
     json: bool,
 
-    /// Follow symlinks (use --no-follow-links to disable)
-    #[arg(long = "no-follow-links", action = clap::ArgAction::SetFalse, default_value_t = true)]
-    follow_links: bool,
+    /// Output format: a JSON array, one JSON object per line, plain paths, or a CSV/TSV table
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "plain")]
+    format: Option<OutputFormat>,
+
+    /// Comma-separated columns for --format csv|tsv (default: path,kind,worktree,root)
+    #[arg(long, value_enum, value_name = "COLUMNS", value_delimiter = ',')]
+    columns: Vec<Column>,
+
+    /// Print one line per repository from a template, e.g. '{worktree}\t{branch}\t{remote.origin}'
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = ["json", "format", "plain", "print0", "columns"]
+    )]
+    format_template: Option<String>,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// Read branch, HEAD commit and remotes for each repository found
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    details: bool,
+
+    /// Count staged, modified and untracked files in each working tree (runs `git status`)
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    status: bool,
+
+    /// Run `git fetch --all --prune` in every repository found; results are
+    /// written once their fetch finishes
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    fetch: bool,
 
-    /// Extra root(s) to scan via flag (can be repeated)
-    #[arg(long, value_name = "PATH")]
-    root: Vec<PathBuf>,
+    /// How many fetches run at once, for --fetch and the TUI's `F` key
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    fetch_jobs: usize,
 
     /// Write the final results to a file instead of stdout
     #[arg(long, value_name = "FILE")]
@@ -45,14 +95,246 @@ struct Args {
     #[arg(long, action = clap::ArgAction::SetTrue)]
     plain: bool,
 
+    /// Which path plain and --print0 output lists for each repository
+    #[arg(long, value_enum, value_name = "WHAT", conflicts_with_all = ["json", "columns", "format_template"])]
+    print: Option<PrintField>,
+
+    /// Output paths terminated by NUL bytes, like `find -print0`
+    #[arg(short = '0', long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["json", "format"])]
+    print0: bool,
+
+    /// Run without the TUI, streaming results as they are found (default when
+    /// stdout or stderr is not a terminal)
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    no_tui: bool,
+
+    /// Print periodic progress lines to stderr when running without the TUI
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    progress: bool,
+
+    /// Config file with custom TUI actions (default:
+    /// $XDG_CONFIG_HOME/find-git-dirs/config.toml)
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Pick one repository: the TUI draws on stderr and Enter prints the selected
+    /// work tree to stdout, so `cd "$(find-git-dirs --pick ~/src)"` works; without
+    /// it, a captured stdout turns the TUI off
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "no_tui")]
+    pick: bool,
+
     /// Root path(s) to scan as positional arguments
     #[arg(value_name = "PATH", num_args = 0.., trailing_var_arg = true)]
     paths: Vec<PathBuf>,
 }
 
+#[derive(clap::Subcommand, Debug)]
+enum CliCommand {
+    /// Run a command in the work tree of every repository found
+    Exec(exec::ExecArgs),
+}
+
+/// Options deciding which repositories a scan reports, shared by the TUI and
+/// the `exec` subcommand.
+#[derive(clap::Args, Debug)]
+struct ScanArgs {
+    /// Follow symlinks (use --no-follow-links to disable)
+    #[arg(long = "no-follow-links", action = clap::ArgAction::SetFalse, default_value_t = true)]
+    follow_links: bool,
+
+    /// Detect bare repositories by their layout (use --no-bare to disable)
+    #[arg(long = "no-bare", action = clap::ArgAction::SetFalse, default_value_t = true)]
+    bare: bool,
+
+    /// Stop at each working tree root instead of descending into it for nested repositories
+    #[arg(long = "no-nested", action = clap::ArgAction::SetFalse, default_value_t = true)]
+    nested: bool,
+
+    /// Only report repositories with a branch ahead of its upstream (implies --details)
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    unpushed: bool,
+
+    /// Only report repositories with no commit, checkout or fetch for this long,
+    /// e.g. 180d, 12w or 2y, oldest first
+    #[arg(long, value_name = "AGE", value_parser = parse_age)]
+    stale_after: Option<u64>,
+
+    /// Extra root(s) to scan via flag (can be repeated); append one `:` followed by
+    /// comma-separated `depth=N` and/or `min-depth=N` to override the depth limits
+    /// for that root, e.g. `~/src:depth=3,min-depth=1`
+    #[arg(long, value_name = "PATH[:OPTS]")]
+    root: Vec<RootSpec>,
+
+    /// Descend at most N directory levels below each root
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Ignore repositories whose `.git` entry is fewer than N levels below the root
+    #[arg(long, value_name = "N")]
+    min_depth: Option<usize>,
+
+    /// Skip paths matching a gitignore-style glob, relative to each root (can be repeated)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Read exclude globs from a file, one per line (can be repeated)
+    #[arg(long, value_name = "FILE")]
+    exclude_from: Vec<PathBuf>,
+
+    /// Walk pseudo-filesystems such as /proc and /sys that are skipped by default
+    #[arg(long = "no-default-excludes", action = clap::ArgAction::SetFalse, default_value_t = true)]
+    default_excludes: bool,
+
+    /// Do not cross filesystem boundaries below each root
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    one_file_system: bool,
+
+    /// Skip network and pseudo mounts listed in /proc/self/mountinfo (Linux only)
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    skip_mounts: bool,
+
+    /// Filesystem type to skip instead of the default list (can be repeated, implies --skip-mounts)
+    #[arg(long, value_name = "TYPE")]
+    skip_fs_type: Vec<String>,
+}
+
+#[derive(Clone)]
+struct ScanOptions {
+    follow_links: bool,
+    bare: bool,
+    nested: bool,
+    details: bool,
+    status: bool,
+    /// Measure the git directory, for the `size` column.
+    size: bool,
+    /// Read `last_activity`, for `--stale-after` and the output.
+    activity: bool,
+    unpushed: bool,
+    /// Leave out repositories active at or after this time, in seconds since
+    /// the Unix epoch, for `--stale-after`.
+    stale_before: Option<i64>,
+    excludes: Vec<String>,
+    default_excludes: bool,
+    one_file_system: bool,
+    /// Mount points to prune, found via `--skip-mounts`.
+    skipped_mounts: Vec<PathBuf>,
+    /// When scanning started, for `found_at_ms`.
+    started: Instant,
+}
+
+/// A root to scan along with its effective depth limits.
+#[derive(Clone, Debug)]
+struct RootSpec {
+    path: PathBuf,
+    max_depth: Option<usize>,
+    min_depth: Option<usize>,
+}
+
+impl RootSpec {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            max_depth: None,
+            min_depth: None,
+        }
+    }
+
+    /// Describes the depth window, e.g. `any`, `≤3` or `2..5`.
+    fn depth_label(&self) -> String {
+        match (self.min_depth.filter(|&d| d > 0), self.max_depth) {
+            (None, None) => "any".to_string(),
+            (None, Some(max)) => format!("≤{}", max),
+            (Some(min), None) => format!("≥{}", min),
+            (Some(min), Some(max)) => format!("{}..{}", min, max),
+        }
+    }
+}
+
+impl FromStr for RootSpec {
+    type Err = String;
+
+    /// Parses `PATH[:depth=N][,min-depth=N]`. A trailing `:...` without an
+    /// `=` is part of the path, so Windows drive letters keep working.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, opts) = match s.rsplit_once(':') {
+            Some((path, opts)) if opts.contains('=') => (path, Some(opts)),
+            _ => (s, None),
+        };
+        // `PATH:depth=3:min-depth=1` would otherwise scan a path that does not exist
+        if path
+            .rsplit_once(':')
+            .is_some_and(|(_, rest)| rest.contains('='))
+        {
+            return Err(
+                "root options go after a single ':' and are separated by commas, e.g. PATH:depth=3,min-depth=1"
+                    .to_string(),
+            );
+        }
+
+        let mut spec = RootSpec::new(expand_home(path));
+        for opt in opts.into_iter().flat_map(|o| o.split(',')) {
+            let (key, value) = opt
+                .split_once('=')
+                .ok_or_else(|| format!("expected KEY=VALUE, got {:?}", opt))?;
+            let n: usize = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid number {:?} for {}", value, key))?;
+            match key.trim() {
+                "depth" | "max-depth" => spec.max_depth = Some(n),
+                "min-depth" => spec.min_depth = Some(n),
+                other => return Err(format!("unknown root option {:?}", other)),
+            }
+        }
+        Ok(spec)
+    }
+}
+
+/// Parses an age such as `180d` into seconds. Units are `s`, `m` (minutes),
+/// `h`, `d`, `w` and `y` (365 days).
+fn parse_age(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (number, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let n: u64 = number.parse().map_err(|_| {
+        format!(
+            "invalid age {:?}, expected a number and a unit like 180d",
+            s
+        )
+    })?;
+    let unit: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        "y" => 365 * 86_400,
+        "" => return Err(format!("missing unit in {:?}; use s, m, h, d, w or y", s)),
+        other => return Err(format!("unknown unit {:?}; use s, m, h, d, w or y", other)),
+    };
+    n.checked_mul(unit)
+        .ok_or_else(|| format!("age {:?} is too large", s))
+}
+
+/// Seconds since the Unix epoch.
+fn unix_now() -> i64 {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    i64::try_from(elapsed.as_secs()).unwrap_or(i64::MAX)
+}
+
+/// Expands a leading `~/`, which the shell leaves alone in `--root=~/src`.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
 #[derive(Clone)]
 struct RootState {
     path: PathBuf,
+    depth: String,
     scanned: u64,
     found: u64,
     done: bool,
@@ -60,9 +342,10 @@ struct RootState {
 }
 
 impl RootState {
-    fn new(path: PathBuf) -> Self {
+    fn new(spec: RootSpec) -> Self {
         Self {
-            path,
+            depth: spec.depth_label(),
+            path: spec.path,
             scanned: 0,
             found: 0,
             done: false,
@@ -71,33 +354,127 @@ impl RootState {
     }
 }
 
+#[derive(Clone, Debug)]
+struct Repo {
+    /// Canonical path of the `.git` entry (a directory, or a file for
+    /// worktrees and submodules).
+    path: PathBuf,
+    /// The git directory `path` resolves to.
+    git_dir: PathBuf,
+    /// The checkout directory, from `core.worktree` or the directory holding
+    /// `path`; bare repositories have none.
+    work_tree: Option<PathBuf>,
+    kind: RepoKind,
+    /// Git directory of the main repository a worktree or submodule belongs to.
+    main: Option<PathBuf>,
+    /// Branch, HEAD and remotes, collected with `--details`.
+    details: Option<git::Details>,
+    /// Working tree changes, collected with `--status`.
+    status: Option<git::Status>,
+    /// Bytes used by the git directory, collected for the `size` column.
+    size: Option<u64>,
+    /// Newest of HEAD's commit date and the `index`, `HEAD` and `FETCH_HEAD`
+    /// modification times, in seconds since the Unix epoch.
+    last_activity: Option<i64>,
+    /// How `git fetch` went, with `--fetch` or the browser's `F` key.
+    fetch: Option<git::FetchOutcome>,
+    /// The scan root this repository was found under, canonicalized like `path`.
+    root: PathBuf,
+    /// Milliseconds from the start of the scan until this repository was found.
+    found_at_ms: u64,
+}
+
+impl Repo {
+    fn new(path: PathBuf, git_dir: PathBuf, kind: RepoKind) -> Self {
+        Self {
+            work_tree: git::work_tree(&path, &git_dir, kind),
+            path,
+            git_dir,
+            kind,
+            main: None,
+            details: None,
+            status: None,
+            size: None,
+            last_activity: None,
+            fetch: None,
+            root: PathBuf::new(),
+            found_at_ms: 0,
+        }
+    }
+}
+
 enum Msg {
     Scanned { root_idx: usize },
     Progress { root_idx: usize, path: PathBuf },
-    Found { root_idx: usize, path: PathBuf },
+    Found { root_idx: usize, repo: Box<Repo> },
     Done { root_idx: usize },
 }
 
 struct App {
     start: Instant,
     roots: Vec<RootState>,
-    recent: Vec<PathBuf>,
-    all_found: Vec<PathBuf>,
+    recent: Vec<Repo>,
+    all_found: Vec<Repo>,
     seen_found: HashSet<PathBuf>,
-    auto_exit_deadline: Option<Instant>,
-    auto_exit_cancelled: bool,
+    /// Browser rows: every result in found order, or the ones matching
+    /// `filter` ranked best first.
+    view: Vec<ViewEntry>,
+    /// Text typed after `/`; `None` when no filter is applied.
+    filter: Option<String>,
+    /// Whether keystrokes go to the filter input.
+    filter_editing: bool,
+    /// Indices into `all_found` chosen with Space, `a` and `i`.
+    marked: BTreeSet<usize>,
+    /// Detail pane contents, keyed by index into `all_found`.
+    info: HashMap<usize, RepoInfo>,
+    /// The result whose details the loader thread was last asked for.
+    info_pending: Option<usize>,
+    /// Message shown in the header, such as a failed action.
+    notice: Option<String>,
+    /// Latest background action per result, keyed by index into `all_found`.
+    action_status: HashMap<usize, ActionStatus>,
+    /// `git fetch` progress, keyed by index into `all_found`.
+    fetch: BTreeMap<usize, FetchState>,
+    /// Results waiting on a fetch, keyed by the common git dir being fetched,
+    /// so linked worktrees share their main repository's fetch.
+    fetch_groups: HashMap<PathBuf, Vec<usize>>,
+    /// Result browser position over `view`.
+    list_state: ListState,
+    /// Rows visible in the browser at the last draw, for PgUp/PgDn.
+    page_size: usize,
+    /// Whether the browser replaces the recent list before the scan is done.
+    browsing: bool,
+    /// Show the working tree status column in the recent list.
+    show_status: bool,
+    /// List and write results oldest activity first, for `--stale-after`.
+    by_age: bool,
+    skipped_mounts: Vec<Mount>,
 }
 
 impl App {
-    fn new(roots: Vec<PathBuf>) -> Self {
+    fn new(roots: Vec<RootSpec>, show_status: bool) -> Self {
         Self {
             start: Instant::now(),
             roots: roots.into_iter().map(RootState::new).collect(),
             recent: Vec::new(),
             all_found: Vec::new(),
             seen_found: HashSet::new(),
-            auto_exit_deadline: None,
-            auto_exit_cancelled: false,
+            view: Vec::new(),
+            filter: None,
+            filter_editing: false,
+            marked: BTreeSet::new(),
+            info: HashMap::new(),
+            info_pending: None,
+            notice: None,
+            action_status: HashMap::new(),
+            fetch: BTreeMap::new(),
+            fetch_groups: HashMap::new(),
+            list_state: ListState::default(),
+            page_size: 1,
+            browsing: false,
+            show_status,
+            by_age: false,
+            skipped_mounts: Vec::new(),
         }
     }
 
@@ -113,173 +490,1211 @@ impl App {
         self.roots.iter().all(|r| r.done)
     }
 
-    fn push_recent(&mut self, p: PathBuf) {
-        self.recent.push(p);
+    /// Applies a scanner message, returning the repository if it is a new find.
+    fn handle(&mut self, msg: Msg) -> Option<&Repo> {
+        match msg {
+            Msg::Scanned { root_idx } => {
+                self.roots[root_idx].scanned = self.roots[root_idx].scanned.saturating_add(1);
+            }
+            Msg::Progress { root_idx, path } => {
+                self.roots[root_idx].current = Some(path);
+            }
+            Msg::Found { root_idx, repo } => {
+                if self.seen_found.insert(repo.path.clone()) {
+                    self.roots[root_idx].found = self.roots[root_idx].found.saturating_add(1);
+                    self.push_recent((*repo).clone());
+                    self.all_found.push(*repo);
+                    self.add_to_view(self.all_found.len() - 1);
+                    return self.all_found.last();
+                }
+            }
+            Msg::Done { root_idx } => {
+                self.roots[root_idx].done = true;
+                self.roots[root_idx].current = None;
+            }
+        }
+        None
+    }
+
+    fn push_recent(&mut self, repo: Repo) {
+        self.recent.push(repo);
         if self.recent.len() > 12 {
             let over = self.recent.len() - 12;
             self.recent.drain(0..over);
         }
     }
 
-    fn refresh_auto_exit(&mut self, now: Instant) {
-        if self.all_done() {
-            if !self.auto_exit_cancelled && self.auto_exit_deadline.is_none() {
-                self.auto_exit_deadline = Some(now + Duration::from_millis(AUTO_EXIT_GRACE_MS));
+    fn show_browser(&self) -> bool {
+        self.browsing || self.all_done()
+    }
+
+    /// Index into `all_found` of the highlighted browser row.
+    fn selected_index(&self) -> Option<usize> {
+        Some(self.view.get(self.list_state.selected()?)?.index)
+    }
+
+    /// Moves the browser cursor by `delta` rows, clamped to the result list.
+    fn move_cursor(&mut self, delta: isize) {
+        if self.view.is_empty() {
+            return;
+        }
+        self.browsing = true;
+        let last = self.view.len() - 1;
+        let current = self.list_state.selected().unwrap_or(0);
+        let next = current.saturating_add_signed(delta).min(last);
+        self.list_state.select(Some(next));
+    }
+
+    fn move_to(&mut self, index: usize) {
+        if self.view.is_empty() {
+            return;
+        }
+        self.browsing = true;
+        self.list_state.select(Some(index.min(self.view.len() - 1)));
+    }
+
+    fn open_filter(&mut self) {
+        self.browsing = true;
+        self.filter_editing = true;
+        self.filter.get_or_insert_with(String::new);
+    }
+
+    /// Leaves the filter input, keeping the filter so the narrowed list can
+    /// be browsed and marked.
+    fn finish_filter(&mut self) {
+        self.filter_editing = false;
+        if self.filter.as_deref() == Some("") {
+            self.clear_filter();
+        }
+    }
+
+    fn clear_filter(&mut self) {
+        self.filter = None;
+        self.filter_editing = false;
+        self.rebuild_view();
+    }
+
+    fn edit_filter(&mut self, edit: impl FnOnce(&mut String)) {
+        if let Some(filter) = self.filter.as_mut() {
+            edit(filter);
+            self.rebuild_view();
+        }
+    }
+
+    /// Asks for the highlighted result's details unless they are loaded or
+    /// already on their way.
+    fn info_request(&mut self) -> Option<InfoRequest> {
+        if !self.show_browser() {
+            return None;
+        }
+        let index = self.selected_index()?;
+        if self.info.contains_key(&index) || self.info_pending == Some(index) {
+            return None;
+        }
+        self.info_pending = Some(index);
+        let repo = &self.all_found[index];
+        Some(InfoRequest {
+            index,
+            git_dir: repo.git_dir.clone(),
+            work_tree: repo.work_tree.clone(),
+        })
+    }
+
+    fn fetch_pending(&self) -> bool {
+        !self.fetch_groups.is_empty()
+    }
+
+    /// Queues a fetch of result `index`, returning the job for the worker
+    /// pool unless its repository is already being fetched.
+    fn queue_fetch(&mut self, index: usize) -> Option<FetchJob> {
+        if matches!(
+            self.fetch.get(&index),
+            Some(FetchState::Queued | FetchState::Running)
+        ) {
+            return None;
+        }
+        let git_dir = &self.all_found[index].git_dir;
+        let common = git::common_dir(git_dir);
+        let group = self.fetch_groups.entry(common.clone()).or_default();
+        group.push(index);
+        if group.len() > 1 {
+            let state = self.fetch[&group[0]];
+            self.fetch.insert(index, state);
+            return None;
+        }
+        self.fetch.insert(index, FetchState::Queued);
+        Some(FetchJob {
+            common,
+            git_dir: git_dir.clone(),
+        })
+    }
+
+    /// Applies a worker pool event, returning the results whose fetch just
+    /// finished.
+    fn handle_fetch(&mut self, event: FetchEvent) -> Vec<usize> {
+        match event {
+            FetchEvent::Started(common) => {
+                for &index in self.fetch_groups.get(&common).into_iter().flatten() {
+                    self.fetch.insert(index, FetchState::Running);
+                }
+                Vec::new()
             }
-        } else {
-            self.auto_exit_deadline = None;
-            self.auto_exit_cancelled = false;
+            FetchEvent::Finished(common, outcome) => {
+                let group = self.fetch_groups.remove(&common).unwrap_or_default();
+                for &index in &group {
+                    self.fetch.insert(index, FetchState::Done);
+                    let repo = &mut self.all_found[index];
+                    // Upstream refs moved, so ahead/behind counts are stale
+                    if repo.details.is_some() {
+                        repo.details = Some(git::read_details(&repo.git_dir));
+                    }
+                    repo.fetch = Some(outcome.clone());
+                    self.forget_info(index);
+                }
+                group
+            }
+        }
+    }
+
+    fn fetch_failures(&self) -> usize {
+        self.all_found
+            .iter()
+            .filter(|r| matches!(r.fetch, Some(git::FetchOutcome::Failed { .. })))
+            .count()
+    }
+
+    /// Drops the detail pane's data for a repository a command may have
+    /// changed, so it is read again on the next draw.
+    fn forget_info(&mut self, index: usize) {
+        self.info.remove(&index);
+        if self.info_pending == Some(index) {
+            self.info_pending = None;
+        }
+    }
+
+    fn store_info(&mut self, index: usize, info: RepoInfo) {
+        if self.info_pending == Some(index) {
+            self.info_pending = None;
         }
+        self.info.insert(index, info);
     }
 
-    fn should_auto_exit(&self, now: Instant) -> bool {
-        matches!(self.auto_exit_deadline, Some(deadline) if now >= deadline)
+    fn toggle_mark(&mut self) {
+        if let Some(index) = self.selected_index() {
+            if !self.marked.remove(&index) {
+                self.marked.insert(index);
+            }
+        }
     }
 
-    fn cancel_auto_exit(&mut self) {
-        self.auto_exit_deadline = None;
-        self.auto_exit_cancelled = true;
+    /// Marks every result in the (possibly filtered) view.
+    fn mark_visible(&mut self) {
+        self.marked.extend(self.view.iter().map(|e| e.index));
+    }
+
+    fn invert_visible(&mut self) {
+        for entry in &self.view {
+            if !self.marked.remove(&entry.index) {
+                self.marked.insert(entry.index);
+            }
+        }
+    }
+
+    /// Matches a result against the filter, trying its path first and then
+    /// its remote URLs.
+    fn match_entry(&self, index: usize) -> Option<ViewEntry> {
+        let pattern = self.filter.as_deref().unwrap_or("");
+        let repo = &self.all_found[index];
+        let path = fuzzy::fuzzy_match(pattern, &repo.path.display().to_string());
+        let remote = repo
+            .details
+            .iter()
+            .flat_map(|d| d.remotes.iter().enumerate())
+            .filter_map(|(i, r)| Some((i, fuzzy::fuzzy_match(pattern, r.url.as_deref()?)?)))
+            .max_by_key(|(_, m)| m.score);
+        match (path, remote) {
+            (Some(path), Some((_, remote))) if remote.score <= path.score => {
+                Some(ViewEntry::on_path(index, path))
+            }
+            (_, Some((remote_idx, remote))) => Some(ViewEntry {
+                index,
+                score: remote.score,
+                path_positions: Vec::new(),
+                remote: Some((remote_idx, remote.positions)),
+            }),
+            (Some(path), None) => Some(ViewEntry::on_path(index, path)),
+            (None, None) => None,
+        }
+    }
+
+    /// Sort key for the view: best filter match first, then with
+    /// `--stale-after` the longest untouched.
+    fn view_key(&self, entry: &ViewEntry) -> (std::cmp::Reverse<i64>, i64) {
+        let age = match self.by_age {
+            true => self.all_found[entry.index]
+                .last_activity
+                .unwrap_or(i64::MAX),
+            false => 0,
+        };
+        (std::cmp::Reverse(entry.score), age)
+    }
+
+    /// Orders result indices for output, oldest activity first with
+    /// `--stale-after` and otherwise as given.
+    fn output_order(&self, mut indices: Vec<usize>) -> Vec<usize> {
+        if self.by_age {
+            indices.sort_by_key(|&i| self.all_found[i].last_activity.unwrap_or(i64::MAX));
+        }
+        indices
+    }
+
+    /// Ranks a newly found result into the view, keeping the cursor on the
+    /// same repository.
+    fn add_to_view(&mut self, index: usize) {
+        let Some(entry) = self.match_entry(index) else {
+            return;
+        };
+        let key = self.view_key(&entry);
+        let at = self.view.partition_point(|e| self.view_key(e) <= key);
+        self.view.insert(at, entry);
+        if let Some(selected) = self.list_state.selected() {
+            if at <= selected {
+                self.list_state.select(Some(selected + 1));
+            }
+        }
+    }
+
+    fn rebuild_view(&mut self) {
+        let selected = self.selected_index();
+        let mut view: Vec<ViewEntry> = (0..self.all_found.len())
+            .filter_map(|i| self.match_entry(i))
+            .collect();
+        // Stable, so equal keys keep the order results were found in
+        view.sort_by_key(|e| self.view_key(e));
+        self.view = view;
+        let row = selected
+            .and_then(|s| self.view.iter().position(|e| e.index == s))
+            .unwrap_or(0);
+        self.list_state
+            .select((!self.view.is_empty()).then_some(row));
     }
 }
 
-const AUTO_EXIT_GRACE_MS: u64 = 1000;
+/// A row of the result browser and where the filter matched it.
+struct ViewEntry {
+    /// Index into `App::all_found`.
+    index: usize,
+    score: i64,
+    /// Matched character positions in the displayed path.
+    path_positions: Vec<usize>,
+    /// Remote whose URL matched, with the matched positions in that URL.
+    remote: Option<(usize, Vec<usize>)>,
+}
+
+impl ViewEntry {
+    fn on_path(index: usize, m: fuzzy::Match) -> Self {
+        Self {
+            index,
+            score: m.score,
+            path_positions: m.positions,
+            remote: None,
+        }
+    }
+}
+
+struct InfoRequest {
+    index: usize,
+    git_dir: PathBuf,
+    work_tree: Option<PathBuf>,
+}
+
+/// Everything the detail pane shows for one repository.
+struct RepoInfo {
+    details: git::Details,
+    /// The commit HEAD points at.
+    commit: Option<odb::Commit>,
+    status: Option<git::Status>,
+    stashes: usize,
+    /// Bytes used by the git directory.
+    size: u64,
+}
+
+impl RepoInfo {
+    fn load(request: &InfoRequest) -> Self {
+        let details = git::read_details(&request.git_dir);
+        let commit = details
+            .head
+            .as_deref()
+            .and_then(|head| git::read_commit(&request.git_dir, head));
+        Self {
+            commit,
+            status: request.work_tree.as_deref().and_then(git::read_status),
+            stashes: git::stash_count(&request.git_dir),
+            size: git::dir_size(&request.git_dir),
+            details,
+        }
+    }
+}
+
+/// Loads detail pane contents off the UI thread. Requests queued while one
+/// is loading are collapsed to the newest, so scrolling quickly through the
+/// list does not build up a backlog of `git status` runs.
+fn spawn_info_loader() -> (Sender<InfoRequest>, Receiver<(usize, RepoInfo)>) {
+    let (request_tx, request_rx) = unbounded::<InfoRequest>();
+    let (info_tx, info_rx) = unbounded();
+    thread::spawn(move || {
+        while let Ok(mut request) = request_rx.recv() {
+            while let Ok(newer) = request_rx.try_recv() {
+                request = newer;
+            }
+            let info = RepoInfo::load(&request);
+            if info_tx.send((request.index, info)).is_err() {
+                break;
+            }
+        }
+    });
+    (request_tx, info_rx)
+}
+
+/// Commands the browser can run on the highlighted repository.
+#[derive(Clone, Copy, Debug)]
+enum RepoAction {
+    /// `$SHELL` in the work tree.
+    Shell,
+    /// `$VISUAL` or `$EDITOR` on the work tree.
+    Editor,
+    /// The desktop's file manager.
+    Open,
+}
+
+impl RepoAction {
+    fn command(self, dir: &Path) -> Command {
+        let mut command = match self {
+            RepoAction::Shell => Command::new(default_shell()),
+            RepoAction::Editor => {
+                let editor = env::var("VISUAL")
+                    .or_else(|_| env::var("EDITOR"))
+                    .unwrap_or_else(|_| "vi".to_string());
+                // Editors are often configured with flags, like `code -w`
+                let mut words = editor.split_whitespace();
+                let mut command = Command::new(words.next().unwrap_or("vi"));
+                command.args(words).arg(".");
+                command
+            }
+            RepoAction::Open => {
+                let mut command = Command::new(OPEN_COMMAND);
+                command.arg(dir);
+                command
+            }
+        };
+        command.current_dir(dir);
+        command
+    }
+}
+
+#[cfg(windows)]
+fn default_shell() -> String {
+    env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string())
+}
+
+#[cfg(not(windows))]
+fn default_shell() -> String {
+    env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+}
+
+#[cfg(target_os = "macos")]
+const OPEN_COMMAND: &str = "open";
+#[cfg(windows)]
+const OPEN_COMMAND: &str = "explorer";
+#[cfg(not(any(target_os = "macos", windows)))]
+const OPEN_COMMAND: &str = "xdg-open";
+
+type Tui = Terminal<CrosstermBackend<Box<dyn Write>>>;
+
+/// Runs `command` with the terminal handed back to it, then restores the
+/// TUI. Returns a message describing a failure, if any.
+fn run_suspended(terminal: &mut Tui, mut command: Command) -> Result<Option<String>> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    // Drawing hides the cursor, so show it for the command; the next draw hides it again
+    terminal.show_cursor()?;
+
+    // Under --pick stdout is captured by the caller, so give the child the
+    // controlling terminal instead
+    if let Some(tty) = controlling_tty() {
+        command
+            .stdin(Stdio::from(tty.try_clone()?))
+            .stdout(Stdio::from(tty.try_clone()?))
+            .stderr(Stdio::from(tty));
+    }
+    let program = command.get_program().to_string_lossy().into_owned();
+    let notice = match command.status() {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(format!("{} exited with {}", program, status)),
+        Err(err) => Some(format!("could not run {}: {}", program, err)),
+    };
+
+    execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+    enable_raw_mode()?;
+    terminal.clear()?;
+    Ok(notice)
+}
+
+#[cfg(unix)]
+fn controlling_tty() -> Option<fs::File> {
+    fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()
+}
+
+#[cfg(not(unix))]
+fn controlling_tty() -> Option<fs::File> {
+    None
+}
+
+/// Progress of a background custom action, shown next to its result.
+enum ActionStatus {
+    Running(String),
+    Finished { name: String, success: bool },
+}
+
+struct ActionDone {
+    index: usize,
+    name: String,
+    /// `None` on success, otherwise what went wrong.
+    failure: Option<String>,
+}
+
+/// Runs a background action on its own thread, reporting back on `done`.
+fn spawn_background_action(
+    mut command: Command,
+    index: usize,
+    name: String,
+    done: Sender<ActionDone>,
+) {
+    thread::spawn(move || {
+        let failure = match command.stdin(Stdio::null()).output() {
+            Ok(output) if output.status.success() => None,
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let last = stderr.lines().rev().find(|l| !l.trim().is_empty());
+                Some(match last {
+                    Some(line) => format!("{}: {}", output.status, line.trim()),
+                    None => output.status.to_string(),
+                })
+            }
+            Err(err) => Some(err.to_string()),
+        };
+        let _ = done.send(ActionDone {
+            index,
+            name,
+            failure,
+        });
+    });
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FetchState {
+    Queued,
+    Running,
+    /// Finished; the outcome is in `Repo::fetch`.
+    Done,
+}
+
+struct FetchJob {
+    /// Common git dir, shared by a repository and its linked worktrees.
+    common: PathBuf,
+    git_dir: PathBuf,
+}
+
+enum FetchEvent {
+    Started(PathBuf),
+    Finished(PathBuf, git::FetchOutcome),
+}
+
+/// Worker threads running `git fetch`, at most `workers` at a time.
+struct FetchPool {
+    jobs: Sender<FetchJob>,
+    events: Receiver<FetchEvent>,
+}
+
+impl FetchPool {
+    fn spawn(workers: usize) -> Self {
+        let (jobs, job_rx) = unbounded::<FetchJob>();
+        let (event_tx, events) = unbounded();
+        for _ in 0..workers.max(1) {
+            let job_rx = job_rx.clone();
+            let event_tx = event_tx.clone();
+            thread::spawn(move || {
+                while let Ok(job) = job_rx.recv() {
+                    let _ = event_tx.send(FetchEvent::Started(job.common.clone()));
+                    let outcome = git::fetch(&job.git_dir);
+                    if event_tx
+                        .send(FetchEvent::Finished(job.common, outcome))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+        Self { jobs, events }
+    }
+
+    fn queue(&self, job: Option<FetchJob>) {
+        if let Some(job) = job {
+            let _ = self.jobs.send(job);
+        }
+    }
+}
+
+/// How the user left the TUI.
+enum Exit {
+    Quit,
+    /// Enter on a single result.
+    Picked(usize),
+    /// Enter with results marked, in the order they were found.
+    Marked(Vec<usize>),
+}
 
 fn main() -> Result<()> {
     let Args {
-        json,
-        follow_links,
-        root,
+        command,
+        json: _,
+        format,
+        columns,
+        format_template,
+        scan,
+        details,
+        status,
+        fetch,
+        fetch_jobs,
         output,
         plain,
+        print0,
+        print,
+        no_tui,
+        progress,
+        pick,
+        config,
         paths,
-    } = Args::parse();
+    } = parse_args();
 
-    let json_output = !plain || json;
+    if let Some(CliCommand::Exec(args)) = command {
+        let code = exec::run(args)?;
+        std::process::exit(code);
+    }
 
-    let mut roots = if paths.is_empty() && root.is_empty() {
-        os_roots()
-    } else {
-        paths
+    let actions = config::load_actions(config.as_deref())?;
+    let template = format_template
+        .as_deref()
+        .map(Template::parse)
+        .transpose()
+        .context("invalid --format-template")?;
+    let format = match format {
+        Some(format) => format,
+        None if template.is_some() => OutputFormat::Template,
+        None if print0 => OutputFormat::Print0,
+        None if plain || print.is_some() => OutputFormat::Plain,
+        None => OutputFormat::Json,
     };
-    roots.extend(root);
-    roots.sort();
-    roots.dedup();
-    roots.retain(|p| p.is_dir());
-    if roots.is_empty() {
-        eprintln!("No valid roots to scan.");
-        return Ok(());
+    let tabular = matches!(format, OutputFormat::Csv | OutputFormat::Tsv);
+    if print.is_some() && !matches!(format, OutputFormat::Plain | OutputFormat::Print0) {
+        anyhow::bail!("--print only applies to --plain and --print0 output");
+    }
+    if !columns.is_empty() && !tabular {
+        anyhow::bail!("--columns only applies to --format csv or --format tsv");
     }
+    let spec = OutputSpec {
+        format,
+        print: print.unwrap_or_default(),
+        columns: if columns.is_empty() {
+            output::DEFAULT_COLUMNS.to_vec()
+        } else {
+            columns
+        },
+        template,
+        fetch,
+    };
+
+    let Some(Scan {
+        roots,
+        mut options,
+        skipped_mounts,
+    }) = prepare_scan(scan, paths)?
+    else {
+        return Ok(());
+    };
+    options.details |=
+        details || spec.needs_details() || actions.iter().any(|a| a.command.needs_details());
+    options.status |=
+        status || spec.needs_status() || actions.iter().any(|a| a.command.needs_status());
+    options.size = spec.needs_size() || actions.iter().any(|a| a.command.needs_size());
+    options.activity |= spec.needs_activity() || actions.iter().any(|a| a.command.needs_activity());
 
     let (tx, rx) = bounded::<Msg>(1024);
-    spawn_scanners(&roots, follow_links, tx)?;
+    spawn_scanners(&roots, &options, tx)?;
 
     let mut live_output = match output.as_ref() {
-        Some(dest) => Some(LiveOutput::new(dest, json_output)?),
+        Some(dest) => Some(LiveOutput::create(dest, &spec)?),
         None => None,
     };
 
+    let mut app = App::new(roots, status);
+    app.skipped_mounts = skipped_mounts;
+    app.by_age = options.stale_before.is_some();
+
+    // Cron jobs, CI and pipes get plain streaming output instead of the TUI.
+    // `--pick` draws on stderr so it works inside `$(...)`.
+    if pick {
+        if !io::stderr().is_terminal() {
+            anyhow::bail!("--pick needs a terminal on stderr");
+        }
+    } else if no_tui || !io::stdout().is_terminal() || !io::stderr().is_terminal() {
+        let sink = match live_output {
+            Some(writer) => writer,
+            None => LiveOutput::new(Box::new(io::stdout()), &spec)?,
+        };
+        let pool = fetch.then(|| FetchPool::spawn(fetch_jobs));
+        return run_headless(app, &rx, sink, progress, pool);
+    }
+
     // TUI setup
     enable_raw_mode()?;
-    let mut stdout = stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    let mut tui_out: Box<dyn Write> = if pick {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    };
+    execute!(tui_out, EnterAlternateScreen)?;
+    let mut terminal: Tui = Terminal::new(CrosstermBackend::new(tui_out))?;
     terminal.clear()?;
 
-    let mut app = App::new(roots);
     let tick_rate = tick(Duration::from_millis(100));
+    let (info_tx, info_rx) = spawn_info_loader();
+    let (action_tx, action_rx) = unbounded::<ActionDone>();
+    let mut fetch_pool = fetch.then(|| FetchPool::spawn(fetch_jobs));
 
     // Event loop
-    loop {
+    let exit = loop {
         // Drain messages fast before drawing
         while let Ok(msg) = rx.try_recv() {
-            match msg {
-                Msg::Scanned { root_idx } => {
-                    app.roots[root_idx].scanned = app.roots[root_idx].scanned.saturating_add(1);
+            if app.handle(msg).is_none() {
+                continue;
+            }
+            let index = app.all_found.len() - 1;
+            // With --fetch, results are written once their fetch is done
+            match &fetch_pool {
+                Some(pool) if fetch => pool.queue(app.queue_fetch(index)),
+                // Sorted by age, so nothing can be written until the end
+                _ if app.by_age => {}
+                _ => {
+                    if let Some(writer) = live_output.as_mut() {
+                        writer.record(&app.all_found[index])?;
+                    }
                 }
-                Msg::Progress { root_idx, path } => {
-                    app.roots[root_idx].current = Some(path);
+            }
+        }
+        while let Some(Ok(event)) = fetch_pool.as_ref().map(|p| p.events.try_recv()) {
+            for index in app.handle_fetch(event) {
+                if let (true, false, Some(writer)) = (fetch, app.by_age, live_output.as_mut()) {
+                    writer.record(&app.all_found[index])?;
                 }
-                Msg::Found { root_idx, path } => {
-                    if app.seen_found.insert(path.clone()) {
-                        app.roots[root_idx].found = app.roots[root_idx].found.saturating_add(1);
-                        app.push_recent(path.clone());
-                        app.all_found.push(path.clone());
-                        if let Some(writer) = live_output.as_mut() {
-                            writer.record(&path)?;
+            }
+        }
+        while let Ok((index, info)) = info_rx.try_recv() {
+            app.store_info(index, info);
+        }
+        while let Ok(done) = action_rx.try_recv() {
+            if let Some(failure) = &done.failure {
+                let repo = &app.all_found[done.index];
+                app.notice = Some(format!(
+                    "{} failed in {}: {}",
+                    done.name,
+                    repo.path.display(),
+                    failure
+                ));
+            }
+            app.action_status.insert(
+                done.index,
+                ActionStatus::Finished {
+                    name: done.name,
+                    success: done.failure.is_none(),
+                },
+            );
+            app.forget_info(done.index);
+        }
+        if let Some(request) = app.info_request() {
+            let _ = info_tx.send(request);
+        }
+
+        terminal.draw(|f| draw(f, &mut app))?;
+
+        // Exit if user quits or all done and user hits Enter
+        select! {
+            recv(tick_rate) -> _ => {},
+            default => {}
+        }
+
+        if event::poll(Duration::from_millis(10))? {
+            if let Event::Key(k) = event::read()? {
+                let page = app.page_size.max(1) as isize;
+                let ctrl = k.modifiers.contains(event::KeyModifiers::CONTROL);
+                let filtering = app.filter_editing;
+                let mut action = None;
+                let mut custom = None;
+                match k.code {
+                    KeyCode::Char('c') if ctrl => break Exit::Quit,
+                    KeyCode::Enter if app.show_browser() && !app.marked.is_empty() => {
+                        break Exit::Marked(app.marked.iter().copied().collect());
+                    }
+                    KeyCode::Enter if app.show_browser() => {
+                        if let Some(index) = app.selected_index() {
+                            break Exit::Picked(index);
+                        }
+                    }
+                    KeyCode::Tab => app.toggle_mark(),
+                    KeyCode::Esc if filtering => app.finish_filter(),
+                    KeyCode::Backspace if filtering => app.edit_filter(|f| {
+                        f.pop();
+                    }),
+                    KeyCode::Char('u') if ctrl && filtering => app.edit_filter(String::clear),
+                    KeyCode::Char(c) if filtering && !ctrl => app.edit_filter(|f| f.push(c)),
+                    KeyCode::Esc if app.filter.is_some() => app.clear_filter(),
+                    KeyCode::Char('q') | KeyCode::Esc => break Exit::Quit,
+                    KeyCode::Char('/') => app.open_filter(),
+                    KeyCode::Char(' ') => {
+                        app.toggle_mark();
+                        app.move_cursor(1);
+                    }
+                    KeyCode::Char('a') => app.mark_visible(),
+                    KeyCode::Char('i') => app.invert_visible(),
+                    KeyCode::Char('s') => action = Some(RepoAction::Shell),
+                    KeyCode::Char('e') => action = Some(RepoAction::Editor),
+                    KeyCode::Char('o') => action = Some(RepoAction::Open),
+                    KeyCode::Char('F') if app.show_browser() => {
+                        let pool = fetch_pool.get_or_insert_with(|| FetchPool::spawn(fetch_jobs));
+                        // Marked results, or every result when none are marked
+                        let indices: Vec<usize> = if app.marked.is_empty() {
+                            (0..app.all_found.len()).collect()
+                        } else {
+                            app.marked.iter().copied().collect()
+                        };
+                        for index in indices {
+                            pool.queue(app.queue_fetch(index));
                         }
                     }
+                    KeyCode::Up | KeyCode::Char('k') => app.move_cursor(-1),
+                    KeyCode::Down | KeyCode::Char('j') => app.move_cursor(1),
+                    KeyCode::PageUp => app.move_cursor(-page),
+                    KeyCode::PageDown => app.move_cursor(page),
+                    KeyCode::Home | KeyCode::Char('g') => app.move_to(0),
+                    KeyCode::End | KeyCode::Char('G') => app.move_to(usize::MAX),
+                    KeyCode::Char(c) if !ctrl => custom = actions.iter().find(|a| a.key == c),
+                    _ => {}
                 }
-                Msg::Done { root_idx } => {
-                    app.roots[root_idx].done = true;
-                    app.roots[root_idx].current = None;
+                if let (Some(action), Some(index)) = (action, app.selected_index()) {
+                    let repo = &app.all_found[index];
+                    let dir = repo.work_tree.as_deref().unwrap_or(&repo.path);
+                    app.notice = run_suspended(&mut terminal, action.command(dir))?;
+                    // A shell or editor may have committed or switched branches
+                    app.forget_info(index);
+                }
+                if let (Some(custom), Some(index)) = (custom, app.selected_index()) {
+                    let command = custom.command_for(&app.all_found[index]);
+                    if custom.background {
+                        app.action_status
+                            .insert(index, ActionStatus::Running(custom.name.clone()));
+                        spawn_background_action(
+                            command,
+                            index,
+                            custom.name.clone(),
+                            action_tx.clone(),
+                        );
+                    } else {
+                        app.notice = run_suspended(&mut terminal, command)?;
+                        app.forget_info(index);
+                    }
+                }
+            }
+        }
+    };
+
+    // Restore terminal
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+
+    // Output results
+    if let Some(writer) = live_output.as_mut() {
+        if app.by_age {
+            for index in app.output_order((0..app.all_found.len()).collect()) {
+                writer.record(&app.all_found[index])?;
+            }
+        } else if fetch {
+            // Results still waiting on --fetch are written without its outcome
+            for (&index, &state) in &app.fetch {
+                if state != FetchState::Done {
+                    writer.record(&app.all_found[index])?;
                 }
             }
         }
+        writer.finalize()?;
+    }
+    // Only --fetch changes the shape of the output, so failed fetches started
+    // with `F` are reported here alone
+    report_fetch_failures(&app);
+    match exit {
+        Exit::Picked(index) => print_picked(&app.all_found[index], print)?,
+        Exit::Marked(indices) if pick => {
+            for index in app.output_order(indices) {
+                print_picked(&app.all_found[index], print)?;
+            }
+        }
+        // Replaces anything streamed to --output with just the chosen results
+        Exit::Marked(indices) => {
+            let chosen: Vec<Repo> = app
+                .output_order(indices)
+                .into_iter()
+                .map(|i| app.all_found[i].clone())
+                .collect();
+            emit_results(&chosen, &spec, output.as_deref())?;
+        }
+        // Nothing was picked, so `cd "$(find-git-dirs --pick)"` should fail
+        Exit::Quit if pick => std::process::exit(1),
+        Exit::Quit if live_output.is_none() => {
+            let all: Vec<Repo> = app
+                .output_order((0..app.all_found.len()).collect())
+                .into_iter()
+                .map(|i| app.all_found[i].clone())
+                .collect();
+            emit_results(&all, &spec, None)?;
+        }
+        Exit::Quit => {}
+    }
+
+    Ok(())
+}
+
+/// Parses the command line, rejecting options given before a subcommand:
+/// they belong to the TUI scan, so `exec` would silently ignore them.
+fn parse_args() -> Args {
+    let mut command = Args::command();
+    let matches = command.get_matches_mut();
+    if let Some((name, _)) = matches.subcommand() {
+        let before = command.get_arguments().find(|arg| {
+            matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+        });
+        if let Some(arg) = before {
+            let flag = match (arg.get_long(), arg.get_short()) {
+                (Some(long), _) => format!("--{}", long),
+                (None, Some(short)) => format!("-{}", short),
+                (None, None) => arg.get_id().to_string().to_uppercase(),
+            };
+            let message = format!(
+                "the argument '{}' cannot be used before the '{}' subcommand",
+                flag, name
+            );
+            command.error(ErrorKind::ArgumentConflict, message).exit();
+        }
+    }
+    Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit())
+}
+
+/// A scan ready to start: its roots, options and the mounts it prunes.
+struct Scan {
+    roots: Vec<RootSpec>,
+    options: ScanOptions,
+    skipped_mounts: Vec<Mount>,
+}
 
-        let now = Instant::now();
-        app.refresh_auto_exit(now);
-        terminal.draw(|f| draw(f, &app))?;
+/// Resolves roots, excludes and skipped mounts from the scan flags. Returns
+/// `None`, after telling the user, when none of the roots exist.
+fn prepare_scan(scan: ScanArgs, paths: Vec<PathBuf>) -> Result<Option<Scan>> {
+    let ScanArgs {
+        follow_links,
+        bare,
+        nested,
+        unpushed,
+        stale_after,
+        root,
+        max_depth,
+        min_depth,
+        mut exclude,
+        exclude_from,
+        default_excludes,
+        one_file_system,
+        skip_mounts,
+        skip_fs_type,
+    } = scan;
+
+    let mut roots: Vec<RootSpec> = if paths.is_empty() && root.is_empty() {
+        os_roots()
+    } else {
+        paths
+    }
+    .into_iter()
+    .map(RootSpec::new)
+    .collect();
+    roots.extend(root);
+    roots.sort_by(|a, b| a.path.cmp(&b.path));
+    // A repeated root keeps any depth overrides given for either occurrence
+    roots.dedup_by(|dup, kept| {
+        let same = dup.path == kept.path;
+        if same {
+            kept.max_depth = kept.max_depth.or(dup.max_depth);
+            kept.min_depth = kept.min_depth.or(dup.min_depth);
+        }
+        same
+    });
+    for spec in &mut roots {
+        spec.max_depth = spec.max_depth.or(max_depth);
+        spec.min_depth = spec.min_depth.or(min_depth);
+    }
+    roots.retain(|r| r.path.is_dir());
+    if roots.is_empty() {
+        eprintln!("No valid roots to scan.");
+        return Ok(None);
+    }
+
+    for file in &exclude_from {
+        let contents = fs::read_to_string(file)
+            .with_context(|| format!("reading exclude file {}", file.display()))?;
+        exclude.extend(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_string),
+        );
+    }
+
+    let skipped_mounts = if skip_mounts || !skip_fs_type.is_empty() {
+        let types: Vec<&str> = if skip_fs_type.is_empty() {
+            DEFAULT_SKIP_FS_TYPES.to_vec()
+        } else {
+            skip_fs_type.iter().map(String::as_str).collect()
+        };
+        let abs_roots: Vec<PathBuf> = roots
+            .iter()
+            .map(|r| canonical_dir(&r.path).unwrap_or_else(|_| r.path.clone()))
+            .collect();
+        // Only mounts strictly below a root can be pruned
+        let mut mounts: Vec<Mount> = read_mounts()
+            .into_iter()
+            .filter(|m| types.contains(&m.fs_type.as_str()))
+            .filter(|m| {
+                abs_roots
+                    .iter()
+                    .any(|r| m.path != *r && m.path.starts_with(r))
+            })
+            .collect();
+        // Mounts nested inside another skipped mount are never reached anyway
+        let all: Vec<PathBuf> = mounts.iter().map(|m| m.path.clone()).collect();
+        mounts.retain(|m| !all.iter().any(|p| *p != m.path && m.path.starts_with(p)));
+        mounts
+    } else {
+        Vec::new()
+    };
+
+    let options = ScanOptions {
+        follow_links,
+        bare,
+        nested,
+        details: unpushed,
+        status: false,
+        size: false,
+        activity: stale_after.is_some(),
+        unpushed,
+        stale_before: stale_after.map(|age| unix_now().saturating_sub_unsigned(age)),
+        excludes: exclude,
+        default_excludes,
+        one_file_system,
+        skipped_mounts: skipped_mounts.iter().map(|m| m.path.clone()).collect(),
+        started: Instant::now(),
+    };
+    Ok(Some(Scan {
+        roots,
+        options,
+        skipped_mounts,
+    }))
+}
 
-        if app.should_auto_exit(Instant::now()) {
-            break;
-        }
+/// Prints the path a picked repository stands for: the `--print` choice, or
+/// else its work tree, falling back to the git dir of a bare repository.
+fn print_picked(repo: &Repo, print: Option<PrintField>) -> Result<()> {
+    let path = match print {
+        Some(print) => print.path(repo),
+        None => Some(repo.work_tree.as_deref().unwrap_or(&repo.path)),
+    };
+    if let Some(path) = path {
+        let mut out = io::stdout();
+        out.write_all(path.as_os_str().as_encoded_bytes())?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
 
-        // Exit if user quits or all done and user hits Enter
-        select! {
-            recv(tick_rate) -> _ => {},
-            default => {}
-        }
+/// Drives the scan without a terminal UI: results stream to `sink` as they
+/// arrive (or as their fetch finishes, with `--fetch`) and, with `--progress`,
+/// a status line goes to stderr every second.
+fn run_headless(
+    app: App,
+    rx: &Receiver<Msg>,
+    sink: LiveOutput,
+    progress: bool,
+    pool: Option<FetchPool>,
+) -> Result<()> {
+    match stream_headless(app, rx, sink, progress, pool) {
+        // The reader went away (`| head`), which is not an error for us
+        Err(e) if is_broken_pipe(&e) => Ok(()),
+        other => other,
+    }
+}
 
-        if app.should_auto_exit(Instant::now()) {
-            break;
-        }
+/// Whether `err` comes from writing to a closed pipe, including io errors
+/// that serde_json wrapped while serializing a record.
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        let kind = match cause.downcast_ref::<serde_json::Error>() {
+            Some(json) => json.io_error_kind(),
+            None => cause.downcast_ref::<io::Error>().map(io::Error::kind),
+        };
+        kind == Some(io::ErrorKind::BrokenPipe)
+    })
+}
 
-        if event::poll(Duration::from_millis(10))? {
-            if let Event::Key(k) = event::read()? {
-                match k.code {
-                    KeyCode::Char('q') | KeyCode::Esc => break,
-                    KeyCode::Char('c') if k.modifiers.contains(event::KeyModifiers::CONTROL) => {
-                        break
+fn stream_headless(
+    mut app: App,
+    rx: &Receiver<Msg>,
+    mut sink: LiveOutput,
+    progress: bool,
+    pool: Option<FetchPool>,
+) -> Result<()> {
+    let report_every = Duration::from_secs(1);
+    let mut last_report = Instant::now();
+    let idle_scan = never();
+    let idle_fetch = never();
+    let fetch_events = pool.as_ref().map_or(&idle_fetch, |p| &p.events);
+
+    while !app.all_done() || app.fetch_pending() {
+        let scan = if app.all_done() { &idle_scan } else { rx };
+        select! {
+            recv(scan) -> msg => {
+                let Ok(msg) = msg else { break };
+                if app.handle(msg).is_some() {
+                    let index = app.all_found.len() - 1;
+                    match &pool {
+                        Some(pool) => pool.queue(app.queue_fetch(index)),
+                        None if app.by_age => {}
+                        None => sink.record(&app.all_found[index])?,
                     }
-                    _ => {
-                        if app.all_done() {
-                            app.cancel_auto_exit();
-                        }
+                }
+            }
+            recv(fetch_events) -> event => {
+                let Ok(event) = event else { break };
+                for index in app.handle_fetch(event) {
+                    if !app.by_age {
+                        sink.record(&app.all_found[index])?;
                     }
                 }
             }
+            default(Duration::from_millis(200)) => {}
         }
 
-        if app.should_auto_exit(Instant::now()) {
-            break;
+        if progress && last_report.elapsed() >= report_every {
+            last_report = Instant::now();
+            eprintln!("{}", progress_line(&app));
         }
     }
 
-    // Restore terminal
-    disable_raw_mode()?;
-    let mut out = io::stdout();
-    execute!(out, LeaveAlternateScreen)?;
+    if progress {
+        eprintln!("{}", progress_line(&app));
+    }
+    // Sorting by age needs every result, so they are written at the end
+    if app.by_age {
+        for index in app.output_order((0..app.all_found.len()).collect()) {
+            sink.record(&app.all_found[index])?;
+        }
+    }
+    report_fetch_failures(&app);
+    sink.finalize()
+}
 
-    // Output results
-    if let Some(writer) = live_output.as_mut() {
-        writer.finalize()?;
+/// Lists the repositories whose fetch failed on stderr.
+fn report_fetch_failures(app: &App) {
+    let failed = app.fetch_failures();
+    if failed == 0 {
+        return;
+    }
+    eprintln!(
+        "find-git-dirs: fetch failed in {} of {} repositories",
+        failed,
+        app.fetch.len()
+    );
+    for repo in &app.all_found {
+        if let Some(git::FetchOutcome::Failed { error }) = &repo.fetch {
+            eprintln!("  {}: {}", repo.path.display(), error.replace('\n', " | "));
+        }
+    }
+}
+
+fn progress_line(app: &App) -> String {
+    let elapsed = app.start.elapsed().as_secs_f64();
+    let scanned = app.total_scanned();
+    let rate = if elapsed > 0.0 {
+        scanned as f64 / elapsed
     } else {
-        emit_results(&app.all_found, json_output, None)?;
+        0.0
+    };
+    let mut line = format!(
+        "find-git-dirs: {}   scanned: {}   found: {}   rate: {:.0}/s   elapsed: {:.1}s   roots done: {}/{}",
+        scan_state(app),
+        scanned,
+        app.total_found(),
+        rate,
+        elapsed,
+        app.roots.iter().filter(|r| r.done).count(),
+        app.roots.len()
+    );
+    if !app.fetch.is_empty() {
+        line.push_str(&format!(
+            "   fetched: {}/{}   failed: {}",
+            app.fetch
+                .values()
+                .filter(|&&s| s == FetchState::Done)
+                .count(),
+            app.fetch.len(),
+            app.fetch_failures()
+        ));
     }
+    line
+}
 
-    Ok(())
+fn scan_state(app: &App) -> &'static str {
+    if !app.all_done() {
+        "scanning"
+    } else if app.fetch_pending() {
+        "fetching"
+    } else {
+        "done"
+    }
 }
 
-fn draw(f: &mut Frame, app: &App) {
+fn draw(f: &mut Frame, app: &mut App) {
     let area = f.area();
 
     let header_height = 3;
     let min_recent_height = 4;
-    let desired_root_height = if app.roots.len() <= 1 {
+    let mut desired_root_height = if app.roots.len() <= 1 {
         4
     } else {
         app.roots.len() as u16 + 3
     };
+    if !app.fetch.is_empty() {
+        desired_root_height = desired_root_height.max(app.fetch.len().min(8) as u16 + 3);
+    }
     let available_for_roots = area
         .height
         .saturating_sub(header_height + min_recent_height)
@@ -304,20 +1719,37 @@ fn draw(f: &mut Frame, app: &App) {
     } else {
         0.0
     };
-    let status = if app.all_done() { "done" } else { "scanning" };
+    let status = scan_state(app);
     let header = Paragraph::new(format!(
-        "state: {}   roots: {}   scanned: {}   found: {}   rate: {:.0}/s   elapsed: {:.1}s   quit: q",
+        "state: {}   roots: {}   scanned: {}   found: {}   selected: {}   rate: {:.0}/s   elapsed: {:.1}s   quit: q",
         status,
         app.roots.len(),
         scanned,
         found,
+        app.marked.len(),
         rate,
         elapsed
     ))
-    .block(Block::default().borders(Borders::ALL).title("find-git-dirs"));
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(match &app.notice {
+                Some(notice) => format!("find-git-dirs — {}", notice),
+                None => "find-git-dirs".to_string(),
+            }),
+    );
     f.render_widget(header, chunks[0]);
 
-    render_root_panel(f, app, chunks[1]);
+    if app.fetch.is_empty() {
+        render_root_panel(f, app, chunks[1]);
+    } else {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[1]);
+        render_root_panel(f, app, halves[0]);
+        render_fetch_table(f, app, halves[1]);
+    }
     render_recent(f, app, chunks[2]);
 }
 
@@ -343,8 +1775,8 @@ fn render_single_root(f: &mut Frame, root: &RootState, area: Rect) {
     let lines = vec![
         Line::from(root.path.display().to_string()),
         Line::from(format!(
-            "status: {}   scanned: {}   found: {}",
-            status, root.scanned, root.found
+            "status: {}   depth: {}   scanned: {}   found: {}",
+            status, root.depth, root.scanned, root.found
         )),
         Line::from(format!("current: {}", current)),
     ];
@@ -370,6 +1802,7 @@ fn render_root_table(f: &mut Frame, roots: &[RootState], area: Rect) {
             };
             Row::new(vec![
                 r.path.display().to_string(),
+                r.depth.clone(),
                 r.scanned.to_string(),
                 r.found.to_string(),
                 status.to_string(),
@@ -382,6 +1815,7 @@ fn render_root_table(f: &mut Frame, roots: &[RootState], area: Rect) {
         rows,
         [
             Constraint::Percentage(30),
+            Constraint::Length(7),
             Constraint::Length(12),
             Constraint::Length(10),
             Constraint::Length(8),
@@ -389,18 +1823,107 @@ fn render_root_table(f: &mut Frame, roots: &[RootState], area: Rect) {
         ],
     )
     .header(
-        Row::new(vec!["root", "scanned", "found", "status", "current path"])
-            .style(Style::default().add_modifier(Modifier::BOLD)),
+        Row::new(vec![
+            "root",
+            "depth",
+            "scanned",
+            "found",
+            "status",
+            "current path",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
     )
     .block(Block::default().borders(Borders::ALL).title("roots"));
     f.render_widget(table, area);
 }
 
-fn render_recent(f: &mut Frame, app: &App, area: Rect) {
+/// Per-repository `git fetch` progress. Running fetches and failures are
+/// listed first, since finished ones need no attention.
+fn render_fetch_table(f: &mut Frame, app: &App, area: Rect) {
+    let outcome = |index: usize| app.all_found[index].fetch.as_ref();
+    let rank = |(&index, &state): (&usize, &FetchState)| match state {
+        FetchState::Running => 0,
+        FetchState::Done if matches!(outcome(index), Some(git::FetchOutcome::Failed { .. })) => 1,
+        FetchState::Queued => 2,
+        FetchState::Done => 3,
+    };
+    let mut entries: Vec<(&usize, &FetchState)> = app.fetch.iter().collect();
+    entries.sort_by_key(|&entry| rank(entry));
+
+    let rows: Vec<Row> = entries
+        .into_iter()
+        .take(area.height.saturating_sub(3) as usize)
+        .map(|(&index, &state)| {
+            let repo = &app.all_found[index];
+            let (label, color, error) = match (state, outcome(index)) {
+                (FetchState::Queued, _) => ("queued", Color::DarkGray, String::new()),
+                (FetchState::Running, _) => ("running", Color::Yellow, String::new()),
+                (FetchState::Done, Some(git::FetchOutcome::Failed { error })) => {
+                    ("failed", Color::Red, error.replace('\n', " | "))
+                }
+                (FetchState::Done, _) => ("ok", Color::Green, String::new()),
+            };
+            Row::new(vec![
+                Cell::from(label).style(Style::default().fg(color)),
+                Cell::from(
+                    repo.work_tree
+                        .as_deref()
+                        .unwrap_or(&repo.path)
+                        .display()
+                        .to_string(),
+                ),
+                Cell::from(error),
+            ])
+        })
+        .collect();
+
+    let done = app
+        .fetch
+        .values()
+        .filter(|&&s| s == FetchState::Done)
+        .count();
+    let title = format!(
+        "fetch ({}/{}, {} failed)",
+        done,
+        app.fetch.len(),
+        app.fetch_failures()
+    );
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(8),
+            Constraint::Percentage(50),
+            Constraint::Percentage(50),
+        ],
+    )
+    .header(
+        Row::new(vec!["status", "repository", "error"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(table, area);
+}
+
+fn render_recent(f: &mut Frame, app: &mut App, area: Rect) {
     if area.height == 0 || area.width == 0 {
         return;
     }
 
+    if app.show_browser() {
+        // The detail pane needs room; narrow terminals get the list alone
+        if area.width >= 80 {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .split(area);
+            render_browser(f, app, chunks[0]);
+            render_detail(f, app, chunks[1]);
+        } else {
+            render_browser(f, app, area);
+        }
+        return;
+    }
+
     if area.height <= 5 {
         render_current_paths(f, app, area);
         return;
@@ -444,6 +1967,18 @@ fn render_current_paths(f: &mut Frame, app: &App, area: Rect) {
         }
     }
 
+    if !app.skipped_mounts.is_empty() {
+        let skipped: Vec<String> = app
+            .skipped_mounts
+            .iter()
+            .map(|m| format!("{} ({})", m.path.display(), m.fs_type))
+            .collect();
+        lines.push(Line::from(vec![
+            Span::styled("skipped mounts: ", Style::default().fg(Color::DarkGray)),
+            Span::raw(skipped.join(", ")),
+        ]));
+    }
+
     let paragraph = Paragraph::new(lines)
         .block(
             Block::default()
@@ -461,12 +1996,23 @@ fn render_recent_list(f: &mut Frame, app: &App, area: Rect) {
 
     let capacity = area.height.saturating_sub(2) as usize;
     let window = capacity.clamp(1, 12);
+    let now = unix_now();
     let start = app.recent.len().saturating_sub(window);
     let items: Vec<ListItem> = app.recent[start..]
         .iter()
         .rev()
         .take(window)
-        .map(|p| ListItem::new(p.display().to_string()))
+        .map(|r| {
+            let mut spans = Vec::new();
+            if app.show_status {
+                spans.push(status_cell(r));
+            }
+            let age = age_cell(r, now);
+            let style = age.as_ref().map(|a| a.style).unwrap_or_default();
+            spans.extend(age);
+            spans.push(Span::styled(recent_label(r), style));
+            ListItem::new(Line::from(spans))
+        })
         .collect();
 
     if items.is_empty() {
@@ -486,29 +2032,520 @@ fn render_recent_list(f: &mut Frame, app: &App, area: Rect) {
     }
 }
 
-fn spawn_scanners(roots: &[PathBuf], follow_links: bool, tx: Sender<Msg>) -> Result<()> {
+/// The full, scrollable result list shown once the scan is done.
+fn render_browser(f: &mut Frame, app: &mut App, area: Rect) {
+    if app.list_state.selected().is_none() && !app.view.is_empty() {
+        app.list_state.select(Some(0));
+    }
+
+    let (list_area, filter_area) = match &app.filter {
+        Some(_) => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(1)])
+                .split(area);
+            (chunks[0], Some(chunks[1]))
+        }
+        None => (area, None),
+    };
+    app.page_size = list_area.height.saturating_sub(2) as usize;
+    let now = unix_now();
+
+    let items: Vec<ListItem> = app
+        .view
+        .iter()
+        .map(|entry| {
+            let repo = &app.all_found[entry.index];
+            let mut spans = vec![if app.marked.contains(&entry.index) {
+                Span::styled("● ", Style::default().fg(Color::Green))
+            } else {
+                Span::raw("  ")
+            }];
+            if app.show_status {
+                spans.push(status_cell(repo));
+            }
+            let mut path = highlight(&repo.path.display().to_string(), &entry.path_positions);
+            if let Some(age) = age_cell(repo, now) {
+                // Unmatched parts of the path take the age colour too
+                for span in &mut path {
+                    if span.style.fg.is_none() {
+                        span.style = age.style;
+                    }
+                }
+                spans.push(age);
+            }
+            spans.extend(path);
+            spans.push(Span::raw(label_suffix(repo)));
+            match app.action_status.get(&entry.index) {
+                Some(ActionStatus::Running(name)) => spans.push(Span::styled(
+                    format!("  [{}: running]", name),
+                    Style::default().fg(Color::Yellow),
+                )),
+                Some(ActionStatus::Finished { name, success }) => spans.push(Span::styled(
+                    format!("  [{}: {}]", name, if *success { "ok" } else { "failed" }),
+                    Style::default().fg(if *success { Color::Green } else { Color::Red }),
+                )),
+                None => {}
+            }
+            if let Some((remote_idx, positions)) = &entry.remote {
+                let details = repo.details.as_ref();
+                if let Some(url) = details.and_then(|d| d.remotes[*remote_idx].url.as_deref()) {
+                    spans.push(Span::raw("  "));
+                    spans.extend(highlight(url, positions));
+                }
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let shown = if app.view.len() == app.all_found.len() {
+        app.view.len().to_string()
+    } else {
+        format!("{} of {}", app.view.len(), app.all_found.len())
+    };
+    let title = match app.list_state.selected() {
+        Some(row) => format!(
+            "results ({}/{})  ↑↓ move  /: filter  space: mark  s/e/o: shell/edit/open  F: fetch  enter: select",
+            row + 1,
+            shown
+        ),
+        None => format!("results ({})  /: filter", shown),
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, list_area, &mut app.list_state);
+
+    if let (Some(filter), Some(filter_area)) = (&app.filter, filter_area) {
+        let hint = if app.filter_editing {
+            "▏  esc: done"
+        } else {
+            "  /: edit  esc: clear"
+        };
+        let input = Line::from(vec![
+            Span::styled("/", Style::default().fg(Color::Yellow)),
+            Span::raw(filter.as_str()),
+            Span::styled(hint, Style::default().fg(Color::DarkGray)),
+        ]);
+        f.render_widget(Paragraph::new(input), filter_area);
+    }
+}
+
+/// Side pane describing the highlighted result.
+fn render_detail(f: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default().borders(Borders::ALL).title("details");
+    let Some(index) = app.selected_index() else {
+        f.render_widget(Paragraph::new("").block(block), area);
+        return;
+    };
+    let repo = &app.all_found[index];
+    let label = Style::default().fg(Color::DarkGray);
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<9}", name), label),
+            Span::raw(value),
+        ])
+    };
+
+    let mut lines = vec![Line::from(Span::styled(
+        repo.work_tree
+            .as_deref()
+            .unwrap_or(&repo.path)
+            .display()
+            .to_string(),
+        Style::default().add_modifier(Modifier::BOLD),
+    ))];
+    lines.push(field("kind", repo.kind.as_str().to_string()));
+    if let Some(last) = repo.last_activity {
+        lines.push(field(
+            "active",
+            format!(
+                "{} ({} ago)",
+                output::format_timestamp(last),
+                age_label(unix_now() - last)
+            ),
+        ));
+    }
+    match (app.fetch.get(&index), &repo.fetch) {
+        (Some(FetchState::Queued), _) => lines.push(field("fetch", "queued".to_string())),
+        (Some(FetchState::Running), _) => lines.push(field("fetch", "running".to_string())),
+        (_, Some(git::FetchOutcome::Ok)) => lines.push(field("fetch", "ok".to_string())),
+        (_, Some(git::FetchOutcome::Failed { error })) => {
+            lines.push(field("fetch", format!("failed: {}", error)))
+        }
+        (_, None) => {}
+    }
+
+    let Some(info) = app.info.get(&index) else {
+        lines.push(Line::from(Span::styled("loading…", label)));
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false });
+        f.render_widget(paragraph, area);
+        return;
+    };
+
+    let details = &info.details;
+    let branch = match (&details.branch, &details.head) {
+        (Some(branch), _) => branch.clone(),
+        (None, Some(head)) => format!("detached at {}", short_id(head)),
+        (None, None) => "unknown".to_string(),
+    };
+    let tracking = details
+        .branch
+        .as_ref()
+        .and_then(|name| details.branches.iter().find(|b| &b.name == name))
+        .and_then(|b| {
+            let upstream = b.upstream.as_ref()?;
+            Some(match b.ahead_behind {
+                Some((ahead, behind)) => format!("  ↑{} ↓{} {}", ahead, behind, upstream),
+                None => format!("  {}", upstream),
+            })
+        })
+        .unwrap_or_default();
+    lines.push(field("branch", branch + &tracking));
+
+    match &info.commit {
+        Some(commit) => {
+            let head = details.head.as_deref().map(short_id).unwrap_or("");
+            lines.push(field("commit", format!("{} {}", head, commit.subject)));
+            lines.push(field("author", commit.author.clone()));
+            lines.push(field("date", output::format_timestamp(commit.commit_time)));
+        }
+        None => lines.push(field("commit", "none".to_string())),
+    }
+
+    let status = match (&info.status, &repo.work_tree) {
+        (Some(s), _) if s.is_clean() => "clean".to_string(),
+        (Some(s), _) => format!(
+            "{} staged, {} modified, {} untracked",
+            s.staged, s.modified, s.untracked
+        ),
+        (None, None) => "bare".to_string(),
+        (None, Some(_)) => "unavailable".to_string(),
+    };
+    lines.push(field("status", status));
+    lines.push(field("stashes", info.stashes.to_string()));
+    lines.push(field("size", format_size(info.size)));
+
+    if details.remotes.is_empty() {
+        lines.push(field("remotes", "none".to_string()));
+    } else {
+        lines.push(Line::from(Span::styled("remotes", label)));
+        for remote in &details.remotes {
+            lines.push(Line::from(format!(
+                "  {}  {}",
+                remote.name,
+                remote.url.as_deref().unwrap_or("")
+            )));
+        }
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    f.render_widget(paragraph, area);
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Splits `text` into spans with the characters at `positions` emphasised.
+fn highlight(text: &str, positions: &[usize]) -> Vec<Span<'static>> {
+    let matched = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    let mut next = positions.iter().peekable();
+    for (i, c) in text.chars().enumerate() {
+        let is_match = next.next_if_eq(&&i).is_some();
+        if is_match != run_matched && !run.is_empty() {
+            let style = if run_matched {
+                matched
+            } else {
+                Style::default()
+            };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
+        }
+        run_matched = is_match;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        let style = if run_matched {
+            matched
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(run, style));
+    }
+    spans
+}
+
+fn recent_label(repo: &Repo) -> String {
+    format!("{}{}", repo.path.display(), label_suffix(repo))
+}
+
+/// Kind and branch annotations shown after a repository's path.
+fn label_suffix(repo: &Repo) -> String {
+    let mut label = String::new();
+    if repo.kind != RepoKind::Repo {
+        label.push_str(&format!("  [{}]", repo.kind.as_str()));
+    }
+    if let Some(details) = &repo.details {
+        match (&details.branch, &details.head) {
+            (Some(branch), _) => label.push_str(&format!("  ({})", branch)),
+            (None, Some(head)) => label.push_str(&format!("  (detached {})", short_id(head))),
+            (None, None) => {}
+        }
+    }
+    label
+}
+
+fn status_cell(repo: &Repo) -> Span<'static> {
+    match &repo.status {
+        Some(s) if s.is_clean() => Span::styled(
+            format!("{:<14}", "clean"),
+            Style::default().fg(Color::Green),
+        ),
+        Some(s) => Span::styled(
+            format!(
+                "{:<14}",
+                format!("+{} ~{} ?{}", s.staged, s.modified, s.untracked)
+            ),
+            Style::default().fg(Color::Yellow),
+        ),
+        None => Span::styled(
+            format!("{:<14}", "n/a"),
+            Style::default().fg(Color::DarkGray),
+        ),
+    }
+}
+
+/// How long ago a repository was last active, coloured from green (this
+/// month) to red (over a year), when `last_activity` was collected.
+fn age_cell(repo: &Repo, now: i64) -> Option<Span<'static>> {
+    let age = now - repo.last_activity?;
+    let days = age / 86_400;
+    let color = if days < 30 {
+        Color::Green
+    } else if days < 180 {
+        Color::Reset
+    } else if days < 365 {
+        Color::Yellow
+    } else {
+        Color::Red
+    };
+    Some(Span::styled(
+        format!("{:>5}  ", age_label(age)),
+        Style::default().fg(color),
+    ))
+}
+
+/// Compact age such as `5h`, `12d`, `7mo` or `3y`.
+fn age_label(secs: i64) -> String {
+    let days = secs / 86_400;
+    if secs < 86_400 {
+        format!("{}h", secs.max(0) / 3_600)
+    } else if days < 60 {
+        format!("{}d", days)
+    } else if days < 730 {
+        format!("{}mo", days / 30)
+    } else {
+        format!("{}y", days / 365)
+    }
+}
+
+fn short_id(id: &str) -> &str {
+    &id[..id.len().min(7)]
+}
+
+/// Pseudo and container filesystems that never hold repositories worth
+/// reporting and are slow or unsafe to walk.
+#[cfg(target_os = "linux")]
+const DEFAULT_EXCLUDES: &[&str] = &[
+    "/proc",
+    "/sys",
+    "/dev",
+    "/run",
+    "/var/lib/docker",
+    "/var/lib/containers",
+];
+
+#[cfg(target_os = "macos")]
+const DEFAULT_EXCLUDES: &[&str] = &["/dev", "/System/Volumes", "/private/var/vm"];
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+const DEFAULT_EXCLUDES: &[&str] = &[];
+
+/// Filesystem types `--skip-mounts` prunes unless `--skip-fs-type` is given.
+const DEFAULT_SKIP_FS_TYPES: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "fuse.sshfs",
+    "proc",
+    "sysfs",
+    "overlay",
+];
+
+#[derive(Clone, Debug)]
+struct Mount {
+    path: PathBuf,
+    fs_type: String,
+}
+
+/// Reads mount points and filesystem types from `/proc/self/mountinfo`.
+#[cfg(target_os = "linux")]
+fn read_mounts() -> Vec<Mount> {
+    let Ok(info) = fs::read_to_string("/proc/self/mountinfo") else {
+        return Vec::new();
+    };
+    info.lines()
+        .filter_map(|line| {
+            // `<id> <parent> <dev> <root> <mount point> <options> [tags...] - <type> ...`
+            let (left, right) = line.split_once(" - ")?;
+            let mount_point = left.split(' ').nth(4)?;
+            let fs_type = right.split(' ').next()?;
+            Some(Mount {
+                path: unescape_mount_path(mount_point),
+                fs_type: fs_type.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_mounts() -> Vec<Mount> {
+    Vec::new()
+}
+
+/// Mount points escape space, tab, newline and backslash as `\ooo` octal.
+#[cfg(target_os = "linux")]
+fn unescape_mount_path(raw: &str) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && i + 3 < bytes.len()
+            && bytes[i + 1..i + 4].iter().all(u8::is_ascii_digit)
+        {
+            let octal = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("0");
+            if let Ok(b) = u8::from_str_radix(octal, 8) {
+                out.push(b);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    PathBuf::from(std::ffi::OsString::from_vec(out))
+}
+
+fn spawn_scanners(roots: &[RootSpec], options: &ScanOptions, tx: Sender<Msg>) -> Result<()> {
+    // Build every root's overrides first so a bad glob fails before any scan starts
+    let overrides = roots
+        .iter()
+        .map(|root| build_overrides(&root.path, options))
+        .collect::<Result<Vec<_>>>()?;
+
     // Spawn one thread per root to avoid blocking the UI
-    for (idx, root) in roots.iter().cloned().enumerate() {
+    for (idx, (root, overrides)) in roots.iter().cloned().zip(overrides).enumerate() {
         let txc = tx.clone();
-        thread::spawn(move || scan_root(idx, &root, follow_links, txc));
+        let options = options.clone();
+        thread::spawn(move || scan_root(idx, &root, &options, overrides, txc));
     }
 
     Ok(())
 }
 
-fn scan_root(root_idx: usize, root: &Path, follow_links: bool, tx: Sender<Msg>) {
-    let mut wb = WalkBuilder::new(root);
+/// Turns `--exclude` globs into ignore overrides for `root`. Built-in
+/// absolute excludes only apply when they fall under the root, so they are
+/// rewritten as globs anchored at it.
+fn build_overrides(root: &Path, options: &ScanOptions) -> Result<Override> {
+    let mut builder = OverrideBuilder::new(root);
+    for glob in &options.excludes {
+        builder
+            .add(&format!("!{}", glob))
+            .with_context(|| format!("invalid exclude glob {:?}", glob))?;
+    }
+
+    let defaults = DEFAULT_EXCLUDES
+        .iter()
+        .filter(|_| options.default_excludes)
+        .map(PathBuf::from);
+    let abs_root = canonical_dir(root).unwrap_or_else(|_| root.to_path_buf());
+    for excluded in defaults.chain(options.skipped_mounts.iter().cloned()) {
+        if let Ok(rel) = excluded.strip_prefix(&abs_root) {
+            if rel.as_os_str().is_empty() {
+                continue;
+            }
+            builder.add(&format!("!/{}", escape_glob(&rel.to_string_lossy())))?;
+        }
+    }
+
+    Ok(builder.build()?)
+}
+
+fn escape_glob(literal: &str) -> String {
+    let mut out = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        match c {
+            '*' | '?' | '[' | ']' | '{' | '}' => {
+                out.push('[');
+                out.push(c);
+                out.push(']');
+            }
+            '\\' => out.push_str("\\\\"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn scan_root(
+    root_idx: usize,
+    root: &RootSpec,
+    options: &ScanOptions,
+    overrides: Override,
+    tx: Sender<Msg>,
+) {
+    let mut wb = WalkBuilder::new(&root.path);
     wb.standard_filters(false)
         .hidden(false)
         .git_ignore(false)
         .git_global(false)
         .git_exclude(false)
-        .follow_links(follow_links);
+        .follow_links(options.follow_links)
+        .same_file_system(options.one_file_system)
+        .max_depth(root.max_depth)
+        .overrides(overrides);
 
     let _ = tx.send(Msg::Progress {
         root_idx,
-        path: root.to_path_buf(),
+        path: root.path.clone(),
     });
+    let min_depth = root.min_depth.unwrap_or(0);
+    // Repository paths are canonical, so the root they are reported under is too
+    let abs_root = &canonical_dir(&root.path).unwrap_or_else(|_| root.path.clone());
     let throttle = Duration::from_millis(120);
     let last_progress = Arc::new(Mutex::new(Instant::now()));
 
@@ -516,6 +2553,20 @@ fn scan_root(root_idx: usize, root: &Path, follow_links: bool, tx: Sender<Msg>)
         let txc = tx.clone();
         let last_progress = Arc::clone(&last_progress);
         Box::new(move |result| {
+            let report = |mut repo: Repo, depth: usize| {
+                if depth < min_depth {
+                    return;
+                }
+                repo.root = abs_root.clone();
+                repo.found_at_ms = options.started.elapsed().as_millis() as u64;
+                collect_details(&mut repo, options);
+                if wanted(&repo, options) {
+                    let _ = txc.send(Msg::Found {
+                        root_idx,
+                        repo: Box::new(repo),
+                    });
+                }
+            };
             match result {
                 Ok(entry) => {
                     let _ = txc.send(Msg::Scanned { root_idx });
@@ -541,9 +2592,23 @@ fn scan_root(root_idx: usize, root: &Path, follow_links: bool, tx: Sender<Msg>)
                         });
                     }
 
-                    if is_git_dir(&entry) {
-                        let path = canonical_dir(entry.path()).unwrap_or_else(|_| path_buf.clone());
-                        let _ = txc.send(Msg::Found { root_idx, path });
+                    let Some(ft) = entry.file_type() else {
+                        return WalkState::Continue;
+                    };
+                    if let Some(repo) = detect_repo(entry.path(), ft, options) {
+                        report(repo, entry.depth());
+                        // Nothing inside a git dir is worth walking
+                        if ft.is_dir() {
+                            return WalkState::Skip;
+                        }
+                    } else if !options.nested
+                        && ft.is_dir()
+                        && root.max_depth.is_none_or(|max| entry.depth() < max)
+                    {
+                        if let Some(repo) = detect_work_tree(entry.path(), options) {
+                            report(repo, entry.depth() + 1);
+                            return WalkState::Skip;
+                        }
                     }
                 }
                 Err(_) => {
@@ -558,14 +2623,75 @@ fn scan_root(root_idx: usize, root: &Path, follow_links: bool, tx: Sender<Msg>)
     let _ = tx.send(Msg::Done { root_idx });
 }
 
-fn is_git_dir(entry: &DirEntry) -> bool {
-    entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false)
-        && entry
-            .path()
-            .file_name()
-            .and_then(OsStr::to_str)
-            .map(|n| n.eq_ignore_ascii_case(".git"))
-            .unwrap_or(false)
+fn detect_repo(entry_path: &Path, ft: FileType, options: &ScanOptions) -> Option<Repo> {
+    let is_dot_git = entry_path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .map(|n| n.eq_ignore_ascii_case(".git"))
+        .unwrap_or(false);
+    if !is_dot_git {
+        if options.bare && ft.is_dir() && git::is_bare_repo(entry_path) {
+            let path = canonical_dir(entry_path).unwrap_or_else(|_| entry_path.to_path_buf());
+            return Some(Repo::new(path.clone(), path, RepoKind::Bare));
+        }
+        if ft.is_dir() && git::has_detached_work_tree(entry_path) {
+            let path = canonical_dir(entry_path).unwrap_or_else(|_| entry_path.to_path_buf());
+            return Some(Repo::new(path.clone(), path, RepoKind::Repo));
+        }
+        return None;
+    }
+
+    let path = canonical_dir(entry_path).unwrap_or_else(|_| entry_path.to_path_buf());
+    if ft.is_dir() {
+        Some(Repo::new(path.clone(), path, RepoKind::Repo))
+    } else if ft.is_file() {
+        // Worktrees and submodules use a `gitdir: <path>` file instead
+        let linked = git::resolve_git_file(entry_path)?;
+        let mut repo = Repo::new(path, linked.git_dir, linked.kind);
+        repo.main = linked.main;
+        Some(repo)
+    } else {
+        None
+    }
+}
+
+/// Looks for a `.git` entry directly inside `dir`, so `--no-nested` can report
+/// a working tree and skip it in one step.
+fn detect_work_tree(dir: &Path, options: &ScanOptions) -> Option<Repo> {
+    let dot_git = dir.join(".git");
+    let meta = if options.follow_links {
+        fs::metadata(&dot_git)
+    } else {
+        fs::symlink_metadata(&dot_git)
+    };
+    detect_repo(&dot_git, meta.ok()?.file_type(), options)
+}
+
+fn collect_details(repo: &mut Repo, options: &ScanOptions) {
+    if options.details {
+        repo.details = Some(git::read_details(&repo.git_dir));
+    }
+    if options.status {
+        if let Some(work_tree) = &repo.work_tree {
+            repo.status = git::read_status(work_tree);
+        }
+    }
+    if options.size {
+        repo.size = Some(git::dir_size(&repo.git_dir));
+    }
+    if options.activity {
+        repo.last_activity = git::last_activity(&repo.git_dir);
+    }
+}
+
+/// Applies result filters such as `--unpushed` and `--stale-after` after
+/// details are collected.
+fn wanted(repo: &Repo, options: &ScanOptions) -> bool {
+    let stale = match options.stale_before {
+        Some(cutoff) => repo.last_activity.is_some_and(|t| t < cutoff),
+        None => true,
+    };
+    stale && (!options.unpushed || repo.details.as_ref().is_some_and(|d| d.has_unpushed()))
 }
 
 fn canonical_dir(p: &Path) -> io::Result<PathBuf> {
@@ -593,124 +2719,119 @@ fn os_roots() -> Vec<PathBuf> {
     vec![PathBuf::from("/")]
 }
 
-fn emit_results(paths: &[PathBuf], json: bool, output: Option<&Path>) -> Result<()> {
-    match output {
-        Some(dest) => write_results(dest, json, paths),
-        None if json => {
-            let stdout = io::stdout();
-            let mut handle = stdout.lock();
-            write_json(&mut handle, paths)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stdout after the reader has gone away, as with `| head -1`.
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
         }
-        None => {
-            let stdout = io::stdout();
-            let mut handle = stdout.lock();
-            for p in paths {
-                writeln!(handle, "{}", p.display())?;
-            }
+
+        fn flush(&mut self) -> io::Result<()> {
             Ok(())
         }
     }
-}
 
-fn write_results(path: &Path, json: bool, paths: &[PathBuf]) -> Result<()> {
-    let file = fs::File::create(path)?;
-    let mut writer = io::BufWriter::new(file);
-    if json {
-        write_json(&mut writer, paths)?;
-    } else {
-        for p in paths {
-            writeln!(writer, "{}", p.display())?;
-        }
+    fn headless_into_closed_pipe(format: OutputFormat) -> Result<()> {
+        let spec = OutputSpec {
+            format,
+            print: PrintField::default(),
+            columns: output::DEFAULT_COLUMNS.to_vec(),
+            template: None,
+            fetch: false,
+        };
+        let sink = LiveOutput::new(Box::new(ClosedPipe), &spec)?;
+        let (tx, rx) = bounded::<Msg>(4);
+        let repo = Repo::new(
+            PathBuf::from("/srv/a/.git"),
+            PathBuf::from("/srv/a/.git"),
+            RepoKind::Repo,
+        );
+        tx.send(Msg::Found {
+            root_idx: 0,
+            repo: Box::new(repo),
+        })?;
+        tx.send(Msg::Done { root_idx: 0 })?;
+        let app = App::new(vec![RootSpec::new(PathBuf::from("/srv"))], false);
+        run_headless(app, &rx, sink, false, None)
     }
-    writer.flush()?;
-    Ok(())
-}
 
-fn write_json<W: Write>(mut writer: W, paths: &[PathBuf]) -> Result<()> {
-    writer.write_all(b"[")?;
-    for (i, p) in paths.iter().enumerate() {
-        if i > 0 {
-            writer.write_all(b",")?;
+    #[test]
+    fn headless_output_stops_quietly_on_a_closed_pipe() {
+        for format in [
+            OutputFormat::Json,
+            OutputFormat::Ndjson,
+            OutputFormat::Plain,
+            OutputFormat::Print0,
+            OutputFormat::Csv,
+            OutputFormat::Tsv,
+        ] {
+            assert!(headless_into_closed_pipe(format).is_ok(), "{:?}", format);
         }
-        writer.write_all(b"\"")?;
-        writer.write_all(escape_json_path(p).as_bytes())?;
-        writer.write_all(b"\"")?;
     }
-    writer.write_all(b"]\n")?;
-    Ok(())
-}
 
-fn escape_json_path(path: &Path) -> String {
-    path.display()
-        .to_string()
-        .replace('\\', "\\\\")
-        .replace('"', "\\\"")
-}
+    #[test]
+    fn recognizes_broken_pipes_wrapped_by_serde() {
+        let json = serde_json::to_writer(ClosedPipe, &"value").unwrap_err();
+        assert!(is_broken_pipe(&json.into()));
+        let io = io::Error::from(io::ErrorKind::BrokenPipe);
+        assert!(is_broken_pipe(&anyhow::Error::from(io).context("writing")));
+        assert!(!is_broken_pipe(&anyhow::anyhow!("other")));
+    }
 
-struct LiveOutput {
-    inner: LiveOutputKind,
-}
+    #[test]
+    fn parses_root_specs() {
+        let spec: RootSpec = "/srv/src:depth=3,min-depth=1".parse().unwrap();
+        assert_eq!(spec.path, Path::new("/srv/src"));
+        assert_eq!((spec.max_depth, spec.min_depth), (Some(3), Some(1)));
 
-enum LiveOutputKind {
-    Json {
-        writer: io::BufWriter<fs::File>,
-        first: bool,
-    },
-    Plain {
-        writer: io::BufWriter<fs::File>,
-    },
-}
+        let spec: RootSpec = "/srv/src:max-depth=2".parse().unwrap();
+        assert_eq!((spec.max_depth, spec.min_depth), (Some(2), None));
 
-impl LiveOutput {
-    fn new(path: &Path, json: bool) -> Result<Self> {
-        let file = fs::File::create(path)?;
-        let mut writer = io::BufWriter::new(file);
-        let inner = if json {
-            writer.write_all(b"[")?;
-            LiveOutputKind::Json {
-                writer,
-                first: true,
-            }
-        } else {
-            LiveOutputKind::Plain { writer }
-        };
-        Ok(Self { inner })
+        // A colon without options is part of the path
+        let spec: RootSpec = "C:".parse().unwrap();
+        assert_eq!(spec.path, Path::new("C:"));
+        assert_eq!((spec.max_depth, spec.min_depth), (None, None));
     }
 
-    fn record(&mut self, path: &Path) -> Result<()> {
-        match &mut self.inner {
-            LiveOutputKind::Json { writer, first } => {
-                if !*first {
-                    writer.write_all(b",")?;
-                }
-                writer.write_all(b"\n  \"")?;
-                writer.write_all(escape_json_path(path).as_bytes())?;
-                writer.write_all(b"\"")?;
-                writer.flush()?;
-                *first = false;
-            }
-            LiveOutputKind::Plain { writer } => {
-                writeln!(writer, "{}", path.display())?;
-                writer.flush()?;
-            }
-        }
-        Ok(())
+    #[test]
+    fn rejects_bad_root_options() {
+        let err = "/srv/src:depth=3:min-depth=1"
+            .parse::<RootSpec>()
+            .unwrap_err();
+        assert!(err.contains("separated by commas"), "{}", err);
+        assert!("/srv/src:depth=x".parse::<RootSpec>().is_err());
+        assert!("/srv/src:width=3".parse::<RootSpec>().is_err());
+        assert!("/srv/src:depth"
+            .parse::<RootSpec>()
+            .is_ok_and(|s| s.max_depth.is_none()));
     }
 
-    fn finalize(&mut self) -> Result<()> {
-        match &mut self.inner {
-            LiveOutputKind::Json { writer, first } => {
-                if *first {
-                    writer.write_all(b"]\n")?;
-                } else {
-                    writer.write_all(b"\n]\n")?;
-                }
-                writer.flush()?;
-            }
-            LiveOutputKind::Plain { writer } => {
-                writer.flush()?;
-            }
+    #[test]
+    fn parses_ages() {
+        assert_eq!(parse_age("90s"), Ok(90));
+        assert_eq!(parse_age("15m"), Ok(900));
+        assert_eq!(parse_age(" 2h "), Ok(7_200));
+        assert_eq!(parse_age("180d"), Ok(180 * 86_400));
+        assert_eq!(parse_age("12w"), Ok(12 * 7 * 86_400));
+        assert_eq!(parse_age("2y"), Ok(2 * 365 * 86_400));
+        for bad in [
+            "180",
+            "d",
+            "1.5d",
+            "-3d",
+            "3 d",
+            "3mo",
+            "99999999999999999999y",
+        ] {
+            assert!(parse_age(bad).is_err(), "{}", bad);
         }
-        Ok(())
+        assert!(parse_age("600000000000000000y")
+            .unwrap_err()
+            .contains("too large"));
     }
 }
@@ -0,0 +1,679 @@
+use flate2::read::ZlibDecoder;
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fs,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// A SHA-1 object id.
+pub type Oid = [u8; 20];
+
+pub fn parse_oid(hex: &str) -> Option<Oid> {
+    let hex = hex.as_bytes();
+    if hex.len() != 40 {
+        return None;
+    }
+    let mut oid = [0u8; 20];
+    for (i, pair) in hex.chunks(2).enumerate() {
+        let hi = (pair[0] as char).to_digit(16)?;
+        let lo = (pair[1] as char).to_digit(16)?;
+        oid[i] = (hi * 16 + lo) as u8;
+    }
+    Some(oid)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectKind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl ObjectKind {
+    fn from_pack_type(t: u8) -> Option<Self> {
+        match t {
+            1 => Some(ObjectKind::Commit),
+            2 => Some(ObjectKind::Tree),
+            3 => Some(ObjectKind::Blob),
+            4 => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"commit" => Some(ObjectKind::Commit),
+            b"tree" => Some(ObjectKind::Tree),
+            b"blob" => Some(ObjectKind::Blob),
+            b"tag" => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Commit {
+    pub parents: Vec<Oid>,
+    /// Committer timestamp, in seconds since the Unix epoch.
+    pub commit_time: i64,
+    /// Author name, without the email address.
+    pub author: String,
+    /// First line of the commit message.
+    pub subject: String,
+}
+
+type Object = Rc<(ObjectKind, Vec<u8>)>;
+
+/// Delta bases decoded while reading packs, keyed by `(pack, offset)`.
+/// Commit chains tend to share bases, so even a small cache pays off.
+const BASE_CACHE_LIMIT: usize = 512;
+
+/// Most bytes reserved up front for an object; larger ones grow as they are
+/// read instead of trusting the size recorded on disk.
+const PREALLOC_LIMIT: usize = 1 << 20;
+
+/// Read-only view of a repository's object database: loose objects plus
+/// version 2 pack indexes, including any `objects/info/alternates`.
+pub struct ObjectStore {
+    object_dirs: Vec<PathBuf>,
+    packs: Vec<Pack>,
+    base_cache: RefCell<HashMap<(usize, u64), Object>>,
+}
+
+impl ObjectStore {
+    pub fn open(common_dir: &Path) -> Self {
+        let primary = common_dir.join("objects");
+        let mut object_dirs = vec![primary.clone()];
+        if let Ok(alternates) = fs::read_to_string(primary.join("info").join("alternates")) {
+            object_dirs.extend(
+                alternates
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                    .map(|l| primary.join(l)),
+            );
+        }
+
+        let mut packs = Vec::new();
+        for dir in &object_dirs {
+            let Ok(entries) = fs::read_dir(dir.join("pack")) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) == Some("idx") {
+                    if let Some(pack) = Pack::open(&path) {
+                        packs.push(pack);
+                    }
+                }
+            }
+        }
+
+        Self {
+            object_dirs,
+            packs,
+            base_cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn read(&self, oid: &Oid) -> Option<Object> {
+        for (i, pack) in self.packs.iter().enumerate() {
+            if let Some(offset) = pack.find(oid) {
+                return self.read_packed(i, offset, 0);
+            }
+        }
+        self.object_dirs
+            .iter()
+            .find_map(|dir| read_loose(dir, oid))
+            .map(Rc::new)
+    }
+
+    pub fn read_commit(&self, oid: &Oid) -> Option<Commit> {
+        let object = self.read(oid)?;
+        match object.0 {
+            ObjectKind::Commit => parse_commit(&object.1),
+            _ => None,
+        }
+    }
+
+    fn read_packed(&self, pack_idx: usize, offset: u64, depth: usize) -> Option<Object> {
+        // Git caps delta chains well below this; deeper means corruption
+        if depth > 512 {
+            return None;
+        }
+        if let Some(hit) = self.base_cache.borrow().get(&(pack_idx, offset)) {
+            return Some(Rc::clone(hit));
+        }
+
+        let pack = &self.packs[pack_idx];
+        let mut file = &pack.file;
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut reader = BufReader::new(file);
+
+        let mut byte = read_u8(&mut reader)?;
+        let pack_type = (byte >> 4) & 0x7;
+        let mut size = (byte & 0x0f) as u64;
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = read_u8(&mut reader)?;
+            size |= shifted((byte & 0x7f) as u64, shift)?;
+            shift += 7;
+        }
+
+        let object = match pack_type {
+            6 => {
+                // OFS_DELTA: base is an earlier entry in the same pack
+                let mut byte = read_u8(&mut reader)?;
+                let mut rel = (byte & 0x7f) as u64;
+                while byte & 0x80 != 0 {
+                    byte = read_u8(&mut reader)?;
+                    rel = shifted(rel.checked_add(1)?, 7)? | (byte & 0x7f) as u64;
+                }
+                let delta = inflate(&mut reader, size)?;
+                let base = self.read_packed(pack_idx, offset.checked_sub(rel)?, depth + 1)?;
+                (base.0, apply_delta(&base.1, &delta)?)
+            }
+            7 => {
+                // REF_DELTA: base named by id, possibly in another pack
+                let mut base_oid = [0u8; 20];
+                reader.read_exact(&mut base_oid).ok()?;
+                let delta = inflate(&mut reader, size)?;
+                let base = self.read(&base_oid)?;
+                (base.0, apply_delta(&base.1, &delta)?)
+            }
+            t => (ObjectKind::from_pack_type(t)?, inflate(&mut reader, size)?),
+        };
+
+        let object = Rc::new(object);
+        let mut cache = self.base_cache.borrow_mut();
+        if cache.len() >= BASE_CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert((pack_idx, offset), Rc::clone(&object));
+        Some(object)
+    }
+
+    /// Counts commits reachable from `local` but not `upstream` (ahead) and
+    /// the reverse (behind). Walks both histories newest-first and stops once
+    /// every commit still queued is reachable from both sides, the same
+    /// approach `git status` uses for its ahead/behind line.
+    pub fn ahead_behind(&self, local: &Oid, upstream: &Oid) -> Option<(usize, usize)> {
+        const LEFT: u8 = 1;
+        const RIGHT: u8 = 2;
+        const BOTH: u8 = LEFT | RIGHT;
+
+        if local == upstream {
+            return Some((0, 0));
+        }
+
+        let mut flags: HashMap<Oid, u8> = HashMap::new();
+        let mut commits: HashMap<Oid, Commit> = HashMap::new();
+        let mut queue: BinaryHeap<(i64, Reverse<Oid>, u8)> = BinaryHeap::new();
+        let mut active = 0usize;
+
+        for (oid, flag) in [(local, LEFT), (upstream, RIGHT)] {
+            let time = self.load_commit(&mut commits, oid)?.commit_time;
+            flags.insert(*oid, flag);
+            queue.push((time, Reverse(*oid), flag));
+            active += 1;
+        }
+
+        while active > 0 {
+            let Some((_, Reverse(oid), pushed)) = queue.pop() else {
+                break;
+            };
+            if pushed != BOTH {
+                active -= 1;
+            }
+            let current = flags[&oid];
+            // A newer entry for this commit carries the merged flags
+            if current != pushed {
+                continue;
+            }
+
+            let parents = commits[&oid].parents.clone();
+            for parent in parents {
+                let Some(time) = self
+                    .load_commit(&mut commits, &parent)
+                    .map(|c| c.commit_time)
+                else {
+                    continue;
+                };
+                let old = flags.get(&parent).copied().unwrap_or(0);
+                let new = old | current;
+                if new != old {
+                    flags.insert(parent, new);
+                    queue.push((time, Reverse(parent), new));
+                    if new != BOTH {
+                        active += 1;
+                    }
+                }
+            }
+        }
+
+        let ahead = flags.values().filter(|&&f| f == LEFT).count();
+        let behind = flags.values().filter(|&&f| f == RIGHT).count();
+        Some((ahead, behind))
+    }
+
+    fn load_commit<'a>(
+        &self,
+        commits: &'a mut HashMap<Oid, Commit>,
+        oid: &Oid,
+    ) -> Option<&'a Commit> {
+        if !commits.contains_key(oid) {
+            let commit = self.read_commit(oid)?;
+            commits.insert(*oid, commit);
+        }
+        commits.get(oid)
+    }
+}
+
+struct Pack {
+    idx: Vec<u8>,
+    count: usize,
+    file: fs::File,
+}
+
+impl Pack {
+    fn open(idx_path: &Path) -> Option<Self> {
+        let idx = fs::read(idx_path).ok()?;
+        // Only the v2 index format (`\377tOc`, version 2) is supported
+        if idx.len() < 8 + 256 * 4 || idx[..4] != [0xff, b't', b'O', b'c'] || be_u32(&idx, 4)? != 2
+        {
+            return None;
+        }
+        let count = be_u32(&idx, 8 + 255 * 4)? as usize;
+        let file = fs::File::open(idx_path.with_extension("pack")).ok()?;
+        Some(Self { idx, count, file })
+    }
+
+    fn find(&self, oid: &Oid) -> Option<u64> {
+        let fanout = |i: usize| be_u32(&self.idx, 8 + i * 4).map(|v| v as usize);
+        let first = oid[0] as usize;
+        let mut lo = if first == 0 { 0 } else { fanout(first - 1)? };
+        let mut hi = fanout(first)?;
+
+        let names = 8 + 256 * 4;
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let name = self.idx.get(names + mid * 20..names + mid * 20 + 20)?;
+            match name.cmp(oid.as_slice()) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => {
+                    let offsets = names + self.count * 24;
+                    let offset = be_u32(&self.idx, offsets + mid * 4)?;
+                    if offset & 0x8000_0000 == 0 {
+                        return Some(offset as u64);
+                    }
+                    let large = offsets + self.count * 4 + (offset & 0x7fff_ffff) as usize * 8;
+                    let bytes = self.idx.get(large..large + 8)?;
+                    return Some(u64::from_be_bytes(bytes.try_into().ok()?));
+                }
+            }
+        }
+        None
+    }
+}
+
+fn read_loose(objects: &Path, oid: &Oid) -> Option<(ObjectKind, Vec<u8>)> {
+    let hex: String = oid.iter().map(|b| format!("{:02x}", b)).collect();
+    let file = fs::File::open(objects.join(&hex[..2]).join(&hex[2..])).ok()?;
+    let mut data = Vec::new();
+    ZlibDecoder::new(file).read_to_end(&mut data).ok()?;
+
+    let nul = data.iter().position(|&b| b == 0)?;
+    let (kind, _size) = data[..nul].split_at(data[..nul].iter().position(|&b| b == b' ')?);
+    let kind = ObjectKind::from_name(kind)?;
+    Some((kind, data.split_off(nul + 1)))
+}
+
+fn inflate<R: Read>(reader: R, size: u64) -> Option<Vec<u8>> {
+    // Sizes come from disk, so a corrupt entry must not decide the allocation
+    let mut out = Vec::with_capacity(size.min(PREALLOC_LIMIT as u64) as usize);
+    ZlibDecoder::new(reader)
+        .take(size)
+        .read_to_end(&mut out)
+        .ok()?;
+    (out.len() as u64 == size).then_some(out)
+}
+
+fn apply_delta(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    let varint = |pos: &mut usize| -> Option<usize> {
+        let mut value = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = *delta.get(*pos)?;
+            *pos += 1;
+            value |= usize::try_from(shifted((byte & 0x7f) as u64, shift)?).ok()?;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    };
+    let base_size = varint(&mut pos)?;
+    let result_size = varint(&mut pos)?;
+    if base_size != base.len() {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(result_size.min(PREALLOC_LIMIT));
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        if op & 0x80 != 0 {
+            // Copy from base: bits 0-3 select offset bytes, bits 4-6 size bytes
+            let mut offset = 0usize;
+            let mut size = 0usize;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= (*delta.get(pos)? as usize) << (i * 8);
+                    pos += 1;
+                }
+            }
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    size |= (*delta.get(pos)? as usize) << (i * 8);
+                    pos += 1;
+                }
+            }
+            if size == 0 {
+                size = 0x10000;
+            }
+            out.extend_from_slice(base.get(offset..offset.checked_add(size)?)?);
+        } else if op != 0 {
+            // Insert the next `op` bytes literally
+            out.extend_from_slice(delta.get(pos..pos + op as usize)?);
+            pos += op as usize;
+        } else {
+            return None;
+        }
+    }
+
+    (out.len() == result_size).then_some(out)
+}
+
+pub fn parse_commit(data: &[u8]) -> Option<Commit> {
+    let text = String::from_utf8_lossy(data);
+    let (headers, message) = text.split_once("\n\n").unwrap_or((&*text, ""));
+
+    let mut parents = Vec::new();
+    let mut commit_time = None;
+    let mut author = String::new();
+    for line in headers.lines() {
+        if let Some(id) = line.strip_prefix("parent ") {
+            parents.push(parse_oid(id.trim())?);
+        } else if let Some(sig) = line.strip_prefix("author ") {
+            author = sig
+                .split_once(" <")
+                .map_or(sig, |(name, _)| name)
+                .to_string();
+        } else if let Some(sig) = line.strip_prefix("committer ") {
+            // `Name <email> 1700000000 +0100`
+            let (_, stamp) = sig.rsplit_once('>')?;
+            commit_time = stamp.split_whitespace().next()?.parse().ok();
+        }
+    }
+
+    Some(Commit {
+        parents,
+        commit_time: commit_time?,
+        author,
+        subject: message.lines().next().unwrap_or("").trim().to_string(),
+    })
+}
+
+/// `value << shift`, or `None` when bits would be lost, as in the variable
+/// length sizes of a corrupt pack.
+fn shifted(value: u64, shift: u32) -> Option<u64> {
+    let result = value.checked_shl(shift)?;
+    (result >> shift == value).then_some(result)
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Option<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf).ok()?;
+    Some(buf[0])
+}
+
+fn be_u32(buf: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(buf.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TestRepo;
+
+    /// A file that grows by a few lines per commit, so its versions deltify.
+    fn notes(lines: usize) -> String {
+        (0..lines)
+            .map(|i| format!("line {} of the running notes, padded to compress\n", i))
+            .collect()
+    }
+
+    /// Two diverging branches plus a merge of part of one into the other:
+    ///
+    /// ```text
+    /// main:   0..6 ─ 6..10
+    ///            └ topic: 5 commits ┐
+    /// merged: main + topic~2 merged, then 2 more
+    /// ```
+    fn diverged_repo() -> TestRepo {
+        let repo = TestRepo::new();
+        for i in 0..6 {
+            repo.commit("notes.txt", &notes(40 * (i + 1)));
+        }
+        repo.git(&["checkout", "-q", "-b", "topic"]);
+        for i in 0..5 {
+            repo.commit("topic.txt", &notes(30 * (i + 1)));
+        }
+        repo.git(&["checkout", "-q", "main"]);
+        for i in 6..10 {
+            repo.commit("notes.txt", &notes(40 * (i + 1)));
+        }
+        repo.git(&["checkout", "-q", "-b", "merged"]);
+        repo.git(&["merge", "-q", "--no-ff", "-m", "Merge topic", "topic~2"]);
+        for i in 0..2 {
+            repo.commit("merged.txt", &notes(10 * (i + 1)));
+        }
+        repo
+    }
+
+    const PAIRS: &[(&str, &str)] = &[
+        ("main", "topic"),
+        ("topic", "main"),
+        ("merged", "main"),
+        ("merged", "topic"),
+        ("topic", "merged"),
+        ("main~3", "main"),
+        ("main", "main~3"),
+        ("main", "main"),
+    ];
+
+    fn assert_ahead_behind_matches_git(repo: &TestRepo) {
+        let store = ObjectStore::open(&repo.git_dir());
+        for (local, upstream) in PAIRS {
+            let oid = |rev: &str| parse_oid(&repo.git(&["rev-parse", rev])).unwrap();
+            let counts = repo.git(&[
+                "rev-list",
+                "--left-right",
+                "--count",
+                &format!("{}...{}", local, upstream),
+            ]);
+            let (ahead, behind) = counts.split_once('\t').unwrap();
+            assert_eq!(
+                store.ahead_behind(&oid(local), &oid(upstream)),
+                Some((ahead.parse().unwrap(), behind.parse().unwrap())),
+                "{}...{}",
+                local,
+                upstream
+            );
+        }
+    }
+
+    /// Pack entry types of the deltified objects, read from the pack itself.
+    fn delta_types(repo: &TestRepo) -> Vec<u8> {
+        let pack_dir = repo.git_dir().join("objects").join("pack");
+        let idx = fs::read_dir(&pack_dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.path())
+            .find(|p| p.extension().is_some_and(|e| e == "idx"))
+            .expect("a pack index");
+        let pack = fs::read(idx.with_extension("pack")).unwrap();
+        // `<id> <type> <size> <packed size> <offset> <depth> <base>` for deltas
+        repo.git(&["verify-pack", "-v", idx.to_str().unwrap()])
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>())
+            .filter(|f| f.len() == 7)
+            .map(|f| (pack[f[4].parse::<usize>().unwrap()] >> 4) & 0x7)
+            .collect()
+    }
+
+    /// Every object in the repository reads back exactly as git prints it.
+    fn assert_objects_match_git(repo: &TestRepo) {
+        let store = ObjectStore::open(&repo.git_dir());
+        let listing = repo.git(&["cat-file", "--batch-all-objects", "--batch-check"]);
+        assert!(!listing.is_empty());
+        for line in listing.lines() {
+            let mut fields = line.split_whitespace();
+            let (id, kind) = (fields.next().unwrap(), fields.next().unwrap());
+            let object = store.read(&parse_oid(id).unwrap()).expect(id);
+            assert_eq!(object.0, ObjectKind::from_name(kind.as_bytes()).unwrap());
+            let expected = std::process::Command::new("git")
+                .args(["cat-file", kind, id])
+                .current_dir(repo.path())
+                .output()
+                .unwrap()
+                .stdout;
+            assert_eq!(object.1, expected, "{} {}", kind, id);
+        }
+    }
+
+    #[test]
+    fn ahead_behind_matches_git_for_loose_objects() {
+        let repo = diverged_repo();
+        assert!(!repo
+            .git_dir()
+            .join("objects")
+            .join("pack")
+            .read_dir()
+            .unwrap()
+            .any(|_| true));
+        assert_ahead_behind_matches_git(&repo);
+        assert_objects_match_git(&repo);
+    }
+
+    #[test]
+    fn ahead_behind_matches_git_for_offset_deltas() {
+        let repo = diverged_repo();
+        repo.git(&["gc", "-q"]);
+        repo.git(&[
+            "repack",
+            "-q",
+            "-a",
+            "-d",
+            "-f",
+            "--depth=50",
+            "--window=50",
+        ]);
+        let types = delta_types(&repo);
+        assert!(
+            !types.is_empty() && types.iter().all(|&t| t == 6),
+            "{:?}",
+            types
+        );
+        assert_ahead_behind_matches_git(&repo);
+        assert_objects_match_git(&repo);
+    }
+
+    #[test]
+    fn ahead_behind_matches_git_for_ref_deltas() {
+        let repo = diverged_repo();
+        repo.git(&[
+            "-c",
+            "repack.useDeltaBaseOffset=false",
+            "repack",
+            "-q",
+            "-a",
+            "-d",
+            "-f",
+            "--depth=50",
+            "--window=50",
+        ]);
+        let types = delta_types(&repo);
+        assert!(
+            !types.is_empty() && types.iter().all(|&t| t == 7),
+            "{:?}",
+            types
+        );
+        assert_ahead_behind_matches_git(&repo);
+        assert_objects_match_git(&repo);
+    }
+
+    #[test]
+    fn reads_objects_split_between_packs_and_loose_files() {
+        let repo = diverged_repo();
+        repo.git(&["gc", "-q"]);
+        repo.git(&["checkout", "-q", "topic"]);
+        for i in 5..8 {
+            repo.commit("topic.txt", &notes(30 * (i + 1)));
+        }
+        assert_ahead_behind_matches_git(&repo);
+        assert_objects_match_git(&repo);
+    }
+
+    #[test]
+    fn rejects_corrupt_sizes_without_panicking() {
+        // A size varint longer than 64 bits
+        let mut delta = vec![0xff; 12];
+        delta.push(0x01);
+        assert_eq!(apply_delta(b"", &delta), None);
+        // A result size far beyond the data, which must not be preallocated
+        let delta = [
+            0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f, 0x01, b'x',
+        ];
+        assert_eq!(apply_delta(b"", &delta), None);
+        // A copy past the end of the base
+        assert_eq!(apply_delta(b"abc", &[0x03, 0x03, 0x91, 0x02, 0x03]), None);
+        assert_eq!(
+            apply_delta(b"abc", &[0x03, 0x02, 0x91, 0x01, 0x02]),
+            Some(b"bc".to_vec())
+        );
+
+        let mut compressed = flate2::write::ZlibEncoder::new(Vec::new(), Default::default());
+        std::io::Write::write_all(&mut compressed, b"short").unwrap();
+        let compressed = compressed.finish().unwrap();
+        assert_eq!(inflate(&compressed[..], u64::MAX), None);
+        assert_eq!(inflate(&compressed[..], 5), Some(b"short".to_vec()));
+
+        assert_eq!(shifted(1, 63), Some(1 << 63));
+        assert_eq!(shifted(2, 63), None);
+        assert_eq!(shifted(1, 70), None);
+    }
+
+    #[test]
+    fn parses_commit_headers() {
+        let data = b"tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\
+parent 1111111111111111111111111111111111111111\n\
+parent 2222222222222222222222222222222222222222\n\
+author Ada Lovelace <ada@example.com> 1700000000 +0100\n\
+committer Grace Hopper <grace@example.com> 1700000123 -0500\n\
+\n\
+Merge things\n\nMore detail.\n";
+        let commit = parse_commit(data).unwrap();
+        assert_eq!(commit.parents, [[0x11; 20], [0x22; 20]]);
+        assert_eq!(commit.commit_time, 1_700_000_123);
+        assert_eq!(commit.author, "Ada Lovelace");
+        assert_eq!(commit.subject, "Merge things");
+    }
+}
@@ -0,0 +1,720 @@
+//! Result serialization for `--format` and `--output`.
+
+use crate::{git, template::Template, Repo};
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use clap::ValueEnum;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Ndjson,
+    Plain,
+    Csv,
+    Tsv,
+    /// NUL-terminated paths, selected with `--print0` rather than `--format`.
+    #[value(skip)]
+    Print0,
+    /// One rendered line per repository, selected with `--format-template`.
+    #[value(skip)]
+    Template,
+}
+
+/// Which path `--plain` and `--print0` output lists for each repository.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PrintField {
+    /// The `.git` entry, or the directory of a bare repository.
+    #[default]
+    Path,
+    GitDir,
+    /// The checkout directory; bare repositories are left out.
+    WorkTree,
+}
+
+impl PrintField {
+    pub fn path(self, repo: &Repo) -> Option<&Path> {
+        match self {
+            PrintField::Path => Some(&repo.path),
+            PrintField::GitDir => Some(&repo.git_dir),
+            PrintField::WorkTree => repo.work_tree.as_deref(),
+        }
+    }
+}
+
+/// A column of `--format csv|tsv` output. Names match the JSON fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum Column {
+    Path,
+    Kind,
+    #[value(alias = "worktree")]
+    WorkTree,
+    #[value(alias = "gitdir")]
+    GitDir,
+    Main,
+    Root,
+    FoundAtMs,
+    Branch,
+    Head,
+    /// URL of `origin`, or of the first remote when there is no `origin`.
+    Remote,
+    /// Commits the checked-out branch is ahead of its upstream.
+    Ahead,
+    Behind,
+    /// Committer date of HEAD, in UTC.
+    LastCommit,
+    /// Newest of the HEAD commit and the last checkout, commit or fetch.
+    LastActivity,
+    Staged,
+    Modified,
+    Untracked,
+    /// Bytes used by the git directory.
+    Size,
+}
+
+pub const DEFAULT_COLUMNS: &[Column] =
+    &[Column::Path, Column::Kind, Column::WorkTree, Column::Root];
+
+impl Column {
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Path => "path",
+            Column::Kind => "kind",
+            Column::WorkTree => "work_tree",
+            Column::GitDir => "git_dir",
+            Column::Main => "main",
+            Column::Root => "root",
+            Column::FoundAtMs => "found_at_ms",
+            Column::Branch => "branch",
+            Column::Head => "head",
+            Column::Remote => "remote",
+            Column::Ahead => "ahead",
+            Column::Behind => "behind",
+            Column::LastCommit => "last_commit",
+            Column::LastActivity => "last_activity",
+            Column::Staged => "staged",
+            Column::Modified => "modified",
+            Column::Untracked => "untracked",
+            Column::Size => "size",
+        }
+    }
+
+    /// Whether the column is filled from `--details` data.
+    pub fn needs_details(self) -> bool {
+        matches!(
+            self,
+            Column::Branch
+                | Column::Head
+                | Column::Remote
+                | Column::Ahead
+                | Column::Behind
+                | Column::LastCommit
+        )
+    }
+
+    /// Whether the column is filled from `--status` data.
+    pub fn needs_status(self) -> bool {
+        matches!(self, Column::Staged | Column::Modified | Column::Untracked)
+    }
+
+    /// The cell for `repo`; `None` when the value is unknown or not applicable.
+    pub fn value(self, repo: &Repo) -> Option<String> {
+        let details = repo.details.as_ref();
+        let current_branch = || {
+            let details = details?;
+            let name = details.branch.as_deref()?;
+            details.branches.iter().find(|b| b.name == name)
+        };
+        match self {
+            Column::Path => Some(repo.path.to_string_lossy().into_owned()),
+            Column::Kind => Some(repo.kind.as_str().to_string()),
+            Column::WorkTree => repo
+                .work_tree
+                .as_ref()
+                .map(|w| w.to_string_lossy().into_owned()),
+            Column::GitDir => Some(repo.git_dir.to_string_lossy().into_owned()),
+            Column::Main => repo.main.as_ref().map(|m| m.to_string_lossy().into_owned()),
+            Column::Root => Some(repo.root.to_string_lossy().into_owned()),
+            Column::FoundAtMs => Some(repo.found_at_ms.to_string()),
+            Column::Branch => details?.branch.clone(),
+            Column::Head => details?.head.clone(),
+            Column::Remote => {
+                let remotes = &details?.remotes;
+                remotes
+                    .iter()
+                    .find(|r| r.name == "origin")
+                    .or_else(|| remotes.first())
+                    .and_then(|r| r.url.clone())
+            }
+            Column::Ahead => current_branch()?.ahead_behind.map(|(a, _)| a.to_string()),
+            Column::Behind => current_branch()?.ahead_behind.map(|(_, b)| b.to_string()),
+            Column::LastCommit => details?.last_commit.map(format_timestamp),
+            Column::LastActivity => repo.last_activity.map(format_timestamp),
+            Column::Staged => repo.status.map(|s| s.staged.to_string()),
+            Column::Modified => repo.status.map(|s| s.modified.to_string()),
+            Column::Untracked => repo.status.map(|s| s.untracked.to_string()),
+            Column::Size => repo.size.map(|s| s.to_string()),
+        }
+    }
+}
+
+/// How results are written: the format plus the path listed by plain output,
+/// the columns used by CSV and TSV or the template used by `--format-template`.
+pub struct OutputSpec {
+    pub format: OutputFormat,
+    pub print: PrintField,
+    pub columns: Vec<Column>,
+    pub template: Option<Template>,
+    /// Results were fetched, so JSON output ends with a `fetch_summary`.
+    pub fetch: bool,
+}
+
+impl OutputSpec {
+    fn table_columns(&self) -> &[Column] {
+        match self.format {
+            OutputFormat::Csv | OutputFormat::Tsv => &self.columns,
+            _ => &[],
+        }
+    }
+
+    /// Whether the output refers to data only collected with `--details`.
+    pub fn needs_details(&self) -> bool {
+        self.table_columns().iter().any(|c| c.needs_details())
+            || self.template.as_ref().is_some_and(Template::needs_details)
+    }
+
+    /// Whether the output refers to data only collected with `--status`.
+    pub fn needs_status(&self) -> bool {
+        self.table_columns().iter().any(|c| c.needs_status())
+            || self.template.as_ref().is_some_and(Template::needs_status)
+    }
+
+    /// Whether the output includes `last_activity`.
+    pub fn needs_activity(&self) -> bool {
+        self.table_columns().contains(&Column::LastActivity)
+            || self.template.as_ref().is_some_and(Template::needs_activity)
+    }
+
+    /// Whether the output includes the size of the git directory.
+    pub fn needs_size(&self) -> bool {
+        self.table_columns().contains(&Column::Size)
+            || self.template.as_ref().is_some_and(Template::needs_size)
+    }
+
+    fn template(&self) -> Result<&Template> {
+        self.template
+            .as_ref()
+            .context("template output requested without a template")
+    }
+}
+
+pub fn emit_results(repos: &[Repo], spec: &OutputSpec, output: Option<&Path>) -> Result<()> {
+    match output {
+        Some(dest) => write_results(dest, spec, repos),
+        None => {
+            let stdout = io::stdout();
+            let mut handle = stdout.lock();
+            write_formatted(&mut handle, spec, repos)
+        }
+    }
+}
+
+fn write_results(path: &Path, spec: &OutputSpec, repos: &[Repo]) -> Result<()> {
+    let file = fs::File::create(path)?;
+    let mut writer = io::BufWriter::new(file);
+    write_formatted(&mut writer, spec, repos)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_formatted<W: Write>(mut writer: W, spec: &OutputSpec, repos: &[Repo]) -> Result<()> {
+    match spec.format {
+        OutputFormat::Json => {
+            let records: Vec<Record> = repos.iter().map(Record).collect();
+            if spec.fetch {
+                writer.write_all(b"{\"repositories\":")?;
+                serde_json::to_writer(&mut writer, &records)?;
+                writer.write_all(b",\"fetch_summary\":")?;
+                serde_json::to_writer(&mut writer, &FetchSummary::from_repos(repos))?;
+                writer.write_all(b"}")?;
+            } else {
+                serde_json::to_writer(&mut writer, &records)?;
+            }
+            writer.write_all(b"\n")?;
+        }
+        OutputFormat::Ndjson => {
+            for r in repos {
+                serde_json::to_writer(&mut writer, &Record(r))?;
+                writer.write_all(b"\n")?;
+            }
+            if spec.fetch {
+                FetchSummary::from_repos(repos).write_line(&mut writer)?;
+            }
+        }
+        OutputFormat::Plain | OutputFormat::Print0 => {
+            let terminator = path_terminator(spec.format);
+            for r in repos {
+                write_path(&mut writer, r, spec.print, terminator)?;
+            }
+        }
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let table = Table::new(spec);
+            table.write_header(&mut writer)?;
+            for r in repos {
+                table.write_row(&mut writer, r)?;
+            }
+        }
+        OutputFormat::Template => {
+            let template = spec.template()?;
+            for r in repos {
+                writeln!(writer, "{}", template.render(r))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// JSON view of a found repository. Paths that are not valid UTF-8 are
+/// written lossily and repeated byte-for-byte as base64 in `<field>_bytes`.
+struct Record<'a>(&'a Repo);
+
+impl Serialize for Record<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repo = self.0;
+        let mut map = serializer.serialize_map(None)?;
+        path_entry(&mut map, "path", Some(&repo.path))?;
+        map.serialize_entry("kind", repo.kind.as_str())?;
+        path_entry(&mut map, "git_dir", Some(&repo.git_dir))?;
+        path_entry(&mut map, "work_tree", repo.work_tree.as_deref())?;
+        path_entry(&mut map, "root", Some(&repo.root))?;
+        map.serialize_entry("found_at_ms", &repo.found_at_ms)?;
+        if let Some(main) = &repo.main {
+            path_entry(&mut map, "main", Some(main))?;
+        }
+        if let Some(details) = &repo.details {
+            map.serialize_entry("branch", &details.branch)?;
+            map.serialize_entry("head", &details.head)?;
+            map.serialize_entry("last_commit", &details.last_commit)?;
+            map.serialize_entry("remotes", &details.remotes)?;
+            let branches: Vec<BranchRecord> = details.branches.iter().map(BranchRecord).collect();
+            map.serialize_entry("branches", &branches)?;
+        }
+        if let Some(status) = &repo.status {
+            map.serialize_entry("status", status)?;
+        }
+        if let Some(size) = repo.size {
+            map.serialize_entry("size", &size)?;
+        }
+        if let Some(last_activity) = repo.last_activity {
+            map.serialize_entry("last_activity", &last_activity)?;
+        }
+        if let Some(fetch) = &repo.fetch {
+            map.serialize_entry("fetch", fetch)?;
+        }
+        map.end()
+    }
+}
+
+/// Tally of `git fetch` outcomes, written after the records in JSON output.
+#[derive(Default)]
+struct FetchSummary {
+    ok: usize,
+    /// Path and error of each repository whose fetch failed.
+    failures: Vec<(PathBuf, String)>,
+}
+
+impl FetchSummary {
+    fn from_repos(repos: &[Repo]) -> Self {
+        let mut summary = Self::default();
+        for repo in repos {
+            summary.add(repo);
+        }
+        summary
+    }
+
+    fn add(&mut self, repo: &Repo) {
+        match &repo.fetch {
+            Some(git::FetchOutcome::Ok) => self.ok += 1,
+            Some(git::FetchOutcome::Failed { error }) => {
+                self.failures.push((repo.path.clone(), error.clone()));
+            }
+            None => {}
+        }
+    }
+
+    /// The NDJSON form: one final `{"fetch_summary": ...}` line.
+    fn write_line<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(b"{\"fetch_summary\":")?;
+        serde_json::to_writer(&mut writer, self)?;
+        writer.write_all(b"}\n")?;
+        Ok(())
+    }
+}
+
+impl Serialize for FetchSummary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let failures: Vec<FetchFailure> = self
+            .failures
+            .iter()
+            .map(|(path, error)| FetchFailure(path, error))
+            .collect();
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("ok", &self.ok)?;
+        map.serialize_entry("failed", &self.failures.len())?;
+        map.serialize_entry("failures", &failures)?;
+        map.end()
+    }
+}
+
+struct FetchFailure<'a>(&'a Path, &'a str);
+
+impl Serialize for FetchFailure<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        path_entry(&mut map, "path", Some(self.0))?;
+        map.serialize_entry("error", self.1)?;
+        map.end()
+    }
+}
+
+struct BranchRecord<'a>(&'a git::Branch);
+
+impl Serialize for BranchRecord<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let branch = self.0;
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("name", &branch.name)?;
+        map.serialize_entry("upstream", &branch.upstream)?;
+        map.serialize_entry("ahead", &branch.ahead_behind.map(|(ahead, _)| ahead))?;
+        map.serialize_entry("behind", &branch.ahead_behind.map(|(_, behind)| behind))?;
+        map.end()
+    }
+}
+
+/// CSV (RFC 4180) or TSV rows for a fixed list of columns.
+struct Table {
+    columns: Vec<Column>,
+    tabs: bool,
+}
+
+impl Table {
+    fn new(spec: &OutputSpec) -> Self {
+        Self {
+            columns: spec.columns.clone(),
+            tabs: spec.format == OutputFormat::Tsv,
+        }
+    }
+
+    fn write_header<W: Write>(&self, writer: W) -> io::Result<()> {
+        let cells: Vec<Option<String>> = self
+            .columns
+            .iter()
+            .map(|c| Some(c.name().to_string()))
+            .collect();
+        self.write_cells(writer, &cells)
+    }
+
+    fn write_row<W: Write>(&self, writer: W, repo: &Repo) -> io::Result<()> {
+        let cells: Vec<Option<String>> = self.columns.iter().map(|c| c.value(repo)).collect();
+        self.write_cells(writer, &cells)
+    }
+
+    fn write_cells<W: Write>(&self, mut writer: W, cells: &[Option<String>]) -> io::Result<()> {
+        let mut line = String::new();
+        for (i, cell) in cells.iter().enumerate() {
+            if i > 0 {
+                line.push(if self.tabs { '\t' } else { ',' });
+            }
+            let cell = cell.as_deref().unwrap_or("");
+            if self.tabs {
+                line.push_str(&escape_tsv(cell));
+            } else {
+                line.push_str(&quote_csv(cell));
+            }
+        }
+        line.push_str(if self.tabs { "\n" } else { "\r\n" });
+        writer.write_all(line.as_bytes())
+    }
+}
+
+/// Quotes a field when it holds a comma, quote or line break, doubling any
+/// embedded quotes, as RFC 4180 requires.
+fn quote_csv(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// TSV has no quoting, so tabs, line breaks and backslashes are escaped.
+fn escape_tsv(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 UTC timestamp.
+pub fn format_timestamp(secs: i64) -> String {
+    let days = secs.div_euclid(86_400);
+    let rem = secs.rem_euclid(86_400);
+    // Civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+fn path_terminator(format: OutputFormat) -> u8 {
+    match format {
+        OutputFormat::Print0 => b'\0',
+        _ => b'\n',
+    }
+}
+
+/// Writes the raw bytes of the chosen path; repositories without one, such as
+/// bare repositories under `--print work-tree`, are skipped.
+fn write_path<W: Write>(
+    mut writer: W,
+    repo: &Repo,
+    print: PrintField,
+    terminator: u8,
+) -> io::Result<()> {
+    let Some(path) = print.path(repo) else {
+        return Ok(());
+    };
+    writer.write_all(path.as_os_str().as_encoded_bytes())?;
+    writer.write_all(&[terminator])
+}
+
+fn path_entry<M: SerializeMap>(
+    map: &mut M,
+    key: &str,
+    path: Option<&Path>,
+) -> Result<(), M::Error> {
+    let Some(path) = path else {
+        return map.serialize_entry(key, &None::<&str>);
+    };
+    match path.to_str() {
+        Some(s) => map.serialize_entry(key, s),
+        None => {
+            map.serialize_entry(key, &path.to_string_lossy())?;
+            let bytes = STANDARD.encode(path.as_os_str().as_encoded_bytes());
+            map.serialize_entry(&format!("{key}_bytes"), &bytes)
+        }
+    }
+}
+
+pub struct LiveOutput {
+    inner: LiveOutputKind,
+    /// Collected while recording when results are fetched; JSON output
+    /// writes it at the end.
+    fetch: Option<FetchSummary>,
+    /// Whether the JSON opening or the table header has been written. It waits
+    /// for the first write so a closed pipe fails where callers expect it.
+    started: bool,
+}
+
+enum LiveOutputKind {
+    Json {
+        writer: Box<dyn Write>,
+        first: bool,
+    },
+    Ndjson {
+        writer: Box<dyn Write>,
+    },
+    Paths {
+        writer: Box<dyn Write>,
+        print: PrintField,
+        terminator: u8,
+    },
+    Table {
+        writer: Box<dyn Write>,
+        table: Table,
+    },
+    Template {
+        writer: Box<dyn Write>,
+        template: Template,
+    },
+}
+
+impl LiveOutput {
+    pub fn create(path: &Path, spec: &OutputSpec) -> Result<Self> {
+        let file = fs::File::create(path)?;
+        Self::new(Box::new(io::BufWriter::new(file)), spec)
+    }
+
+    pub fn new(writer: Box<dyn Write>, spec: &OutputSpec) -> Result<Self> {
+        let inner = match spec.format {
+            OutputFormat::Json => LiveOutputKind::Json {
+                writer,
+                first: true,
+            },
+            OutputFormat::Ndjson => LiveOutputKind::Ndjson { writer },
+            OutputFormat::Plain | OutputFormat::Print0 => LiveOutputKind::Paths {
+                writer,
+                print: spec.print,
+                terminator: path_terminator(spec.format),
+            },
+            OutputFormat::Csv | OutputFormat::Tsv => LiveOutputKind::Table {
+                writer,
+                table: Table::new(spec),
+            },
+            OutputFormat::Template => LiveOutputKind::Template {
+                writer,
+                template: spec.template()?.clone(),
+            },
+        };
+        Ok(Self {
+            inner,
+            fetch: spec.fetch.then(FetchSummary::default),
+            started: false,
+        })
+    }
+
+    /// Writes what comes before the first result: the opening of a JSON
+    /// array, or the header row of a table.
+    fn start(&mut self) -> io::Result<()> {
+        if std::mem::replace(&mut self.started, true) {
+            return Ok(());
+        }
+        match &mut self.inner {
+            LiveOutputKind::Json { writer, .. } => {
+                if self.fetch.is_some() {
+                    writer.write_all(b"{\"repositories\":")?;
+                }
+                writer.write_all(b"[")
+            }
+            LiveOutputKind::Table { writer, table } => table.write_header(&mut *writer),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record(&mut self, repo: &Repo) -> Result<()> {
+        self.start()?;
+        if let Some(summary) = self.fetch.as_mut() {
+            summary.add(repo);
+        }
+        match &mut self.inner {
+            LiveOutputKind::Json { writer, first } => {
+                if !*first {
+                    writer.write_all(b",")?;
+                }
+                writer.write_all(b"\n  ")?;
+                serde_json::to_writer(&mut *writer, &Record(repo))?;
+                writer.flush()?;
+                *first = false;
+            }
+            LiveOutputKind::Ndjson { writer } => {
+                serde_json::to_writer(&mut *writer, &Record(repo))?;
+                writer.write_all(b"\n")?;
+                writer.flush()?;
+            }
+            LiveOutputKind::Paths {
+                writer,
+                print,
+                terminator,
+            } => {
+                write_path(&mut *writer, repo, *print, *terminator)?;
+                writer.flush()?;
+            }
+            LiveOutputKind::Table { writer, table } => {
+                table.write_row(&mut *writer, repo)?;
+                writer.flush()?;
+            }
+            LiveOutputKind::Template { writer, template } => {
+                writeln!(writer, "{}", template.render(repo))?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finalize(&mut self) -> Result<()> {
+        self.start()?;
+        match &mut self.inner {
+            LiveOutputKind::Json { writer, first } => {
+                if !*first {
+                    writer.write_all(b"\n")?;
+                }
+                writer.write_all(b"]")?;
+                if let Some(summary) = &self.fetch {
+                    writer.write_all(b",\"fetch_summary\":")?;
+                    serde_json::to_writer(&mut *writer, summary)?;
+                    writer.write_all(b"}")?;
+                }
+                writer.write_all(b"\n")?;
+                writer.flush()?;
+            }
+            LiveOutputKind::Ndjson { writer } => {
+                if let Some(summary) = &self.fetch {
+                    summary.write_line(&mut *writer)?;
+                }
+                writer.flush()?;
+            }
+            LiveOutputKind::Paths { writer, .. }
+            | LiveOutputKind::Table { writer, .. }
+            | LiveOutputKind::Template { writer, .. } => {
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_csv_fields_only_when_needed() {
+        assert_eq!(quote_csv("/srv/plain"), "/srv/plain");
+        assert_eq!(quote_csv("a,b"), "\"a,b\"");
+        assert_eq!(quote_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(quote_csv("two\nlines"), "\"two\nlines\"");
+        assert_eq!(quote_csv(""), "");
+    }
+
+    #[test]
+    fn escapes_tsv_fields() {
+        assert_eq!(escape_tsv("/srv/plain"), "/srv/plain");
+        assert_eq!(escape_tsv("a\tb\nc\rd"), "a\\tb\\nc\\rd");
+        assert_eq!(escape_tsv("C:\\src"), "C:\\\\src");
+    }
+
+    #[test]
+    fn formats_utc_timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(format_timestamp(-1), "1969-12-31T23:59:59Z");
+    }
+}
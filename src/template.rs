@@ -0,0 +1,312 @@
+//! `--format-template` parsing and rendering.
+//!
+//! A template is literal text with `{field}` or `{field|filter|...}`
+//! placeholders. `{{` and `}}` produce literal braces, and `\t`, `\n`, `\r`,
+//! `\0` and `\\` are unescaped so templates can be written in single quotes.
+
+use crate::{output::Column, Repo};
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Clone, Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Literal(String),
+    Field { field: Field, filters: Vec<Filter> },
+}
+
+#[derive(Clone, Debug)]
+enum Field {
+    /// Any of the `--columns` names, such as `path` or `branch`.
+    Column(Column),
+    /// Directory containing the repository (the work tree, or a bare repository itself).
+    Parent,
+    /// Final component of the repository directory.
+    Name,
+    /// Repository directory relative to its scan root.
+    RootRel,
+    /// URL of the named remote.
+    Remote(String),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Filter {
+    Basename,
+    Dirname,
+    Relpath,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => bail!("unmatched '}}' in template (write '}}}}' for a literal brace)"),
+                '{' => {
+                    let mut spec = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => spec.push(c),
+                            None => bail!("unclosed '{{' in template"),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_placeholder(&spec)?);
+                }
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('r') => literal.push('\r'),
+                    Some('0') => literal.push('\0'),
+                    Some('\\') => literal.push('\\'),
+                    Some(other) => {
+                        literal.push('\\');
+                        literal.push(other);
+                    }
+                    None => literal.push('\\'),
+                },
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Expands the template for `repo`. Missing values render as empty text.
+    pub fn render(&self, repo: &Repo) -> String {
+        self.render_with(repo, str::to_string)
+    }
+
+    /// Expands the template, passing each substituted value through `quote`
+    /// so it can be embedded in a shell command line.
+    pub fn render_with(&self, repo: &Repo, quote: impl Fn(&str) -> String) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Field { field, filters } => {
+                    let mut value = field.value(repo).unwrap_or_default();
+                    for filter in filters {
+                        value = filter.apply(&value, repo);
+                    }
+                    out.push_str(&quote(&value));
+                }
+            }
+        }
+        out
+    }
+
+    pub fn needs_details(&self) -> bool {
+        self.fields().any(|f| match f {
+            Field::Column(c) => c.needs_details(),
+            Field::Remote(_) => true,
+            _ => false,
+        })
+    }
+
+    pub fn needs_status(&self) -> bool {
+        self.fields()
+            .any(|f| matches!(f, Field::Column(c) if c.needs_status()))
+    }
+
+    pub fn needs_size(&self) -> bool {
+        self.fields()
+            .any(|f| matches!(f, Field::Column(Column::Size)))
+    }
+
+    pub fn needs_activity(&self) -> bool {
+        self.fields()
+            .any(|f| matches!(f, Field::Column(Column::LastActivity)))
+    }
+
+    fn fields(&self) -> impl Iterator<Item = &Field> {
+        self.parts.iter().filter_map(|p| match p {
+            Part::Field { field, .. } => Some(field),
+            Part::Literal(_) => None,
+        })
+    }
+}
+
+fn parse_placeholder(spec: &str) -> Result<Part> {
+    let mut pieces = spec.split('|').map(str::trim);
+    let name = pieces.next().unwrap_or_default();
+    let field = match name {
+        "parent" => Field::Parent,
+        "name" => Field::Name,
+        "root_rel" => Field::RootRel,
+        _ => match name.strip_prefix("remote.") {
+            Some(remote) if !remote.is_empty() => Field::Remote(remote.to_string()),
+            _ => match Column::from_str(name, false) {
+                Ok(column) => Field::Column(column),
+                Err(_) => bail!("unknown template field {{{}}}", name),
+            },
+        },
+    };
+    let filters = pieces
+        .map(|filter| match filter {
+            "basename" => Ok(Filter::Basename),
+            "dirname" => Ok(Filter::Dirname),
+            "relpath" => Ok(Filter::Relpath),
+            other => bail!("unknown template filter |{} in {{{}}}", other, spec),
+        })
+        .collect::<Result<_>>()?;
+    Ok(Part::Field { field, filters })
+}
+
+/// The directory a person thinks of as "the repository".
+fn repo_dir(repo: &Repo) -> &Path {
+    repo.work_tree.as_deref().unwrap_or(&repo.path)
+}
+
+impl Field {
+    fn value(&self, repo: &Repo) -> Option<String> {
+        match self {
+            Field::Column(column) => column.value(repo),
+            Field::Parent => repo_dir(repo)
+                .parent()
+                .map(|p| p.to_string_lossy().into_owned()),
+            Field::Name => repo_dir(repo)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned()),
+            Field::RootRel => Some(relative_to_root(&repo_dir(repo).to_string_lossy(), repo)),
+            Field::Remote(name) => repo
+                .details
+                .as_ref()?
+                .remotes
+                .iter()
+                .find(|r| &r.name == name)?
+                .url
+                .clone(),
+        }
+    }
+}
+
+impl Filter {
+    fn apply(self, value: &str, repo: &Repo) -> String {
+        let path = Path::new(value);
+        match self {
+            Filter::Basename => path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            Filter::Dirname => path
+                .parent()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            Filter::Relpath => relative_to_root(value, repo),
+        }
+    }
+}
+
+/// `value` relative to the repository's scan root, climbing out of it with
+/// `..` when needed. Empty when `value` is not an absolute path, since there
+/// is then nothing to relate to the root.
+fn relative_to_root(value: &str, repo: &Repo) -> String {
+    let path = Path::new(value);
+    if !path.is_absolute() {
+        return String::new();
+    }
+    let mut root = repo.root.components().peekable();
+    let mut rest = path.components().peekable();
+    let mut shared = 0;
+    while let (Some(a), Some(b)) = (root.peek(), rest.peek()) {
+        if a != b {
+            break;
+        }
+        root.next();
+        rest.next();
+        shared += 1;
+    }
+    // Different drives on Windows have no relative path between them
+    if shared == 0 {
+        return String::new();
+    }
+    let rel: PathBuf = root.map(|_| Component::ParentDir).chain(rest).collect();
+    if rel.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        rel.to_string_lossy().into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::RepoKind;
+
+    fn repo() -> Repo {
+        let mut repo = Repo::new(
+            PathBuf::from("/srv/src/tools/a/.git"),
+            PathBuf::from("/srv/src/tools/a/.git"),
+            RepoKind::Repo,
+        );
+        repo.work_tree = Some(PathBuf::from("/srv/src/tools/a"));
+        repo.root = PathBuf::from("/srv/src");
+        repo
+    }
+
+    fn render(template: &str) -> String {
+        Template::parse(template).unwrap().render(&repo())
+    }
+
+    #[test]
+    fn renders_fields_and_filters() {
+        assert_eq!(render("{name}\t{parent}"), "a\t/srv/src/tools");
+        assert_eq!(render("{root_rel}|{path|relpath}"), "tools/a|tools/a/.git");
+        assert_eq!(render("{work_tree|dirname|basename}"), "tools");
+        assert_eq!(render("{ worktree | relpath }"), "tools/a");
+        assert_eq!(render("{root|relpath}"), ".");
+        // Unknown values expand to nothing
+        assert_eq!(render("[{branch}][{remote.origin}]"), "[][]");
+    }
+
+    #[test]
+    fn unescapes_literals() {
+        assert_eq!(render("{{{name}}}\\n\\0\\\\\\q"), "{a}\n\0\\\\q");
+    }
+
+    #[test]
+    fn rejects_bad_placeholders() {
+        for bad in ["{nope}", "{name|upper}", "{name", "{remote.}"] {
+            assert!(Template::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn relpath_climbs_out_of_the_root() {
+        let repo = repo();
+        assert_eq!(relative_to_root("/srv/src/tools/a", &repo), "tools/a");
+        assert_eq!(relative_to_root("/srv/src", &repo), ".");
+        assert_eq!(relative_to_root("/srv/other/b", &repo), "../other/b");
+        assert_eq!(relative_to_root("main", &repo), "");
+    }
+
+    #[test]
+    fn reports_what_the_template_needs() {
+        let template = Template::parse("{branch} {modified} {size}").unwrap();
+        assert!(template.needs_details() && template.needs_status() && template.needs_size());
+        assert!(!template.needs_activity());
+        assert!(!Template::parse("{remote.origin}").unwrap().needs_status());
+        assert!(Template::parse("{remote.origin}").unwrap().needs_details());
+    }
+}
@@ -0,0 +1,90 @@
+//! Scratch git repositories for tests, built with the `git` command so the
+//! readers in `git` and `odb` can be checked against git itself.
+
+use std::{
+    cell::Cell,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A repository in a fresh temporary directory, removed again on drop.
+/// Every commit gets a committer date a minute after the previous one, so
+/// history order does not depend on how fast the test runs.
+pub struct TestRepo {
+    dir: PathBuf,
+    clock: Cell<i64>,
+}
+
+impl TestRepo {
+    pub fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "find-git-dirs-test-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create scratch directory");
+        let repo = Self {
+            dir,
+            clock: Cell::new(1_700_000_000),
+        };
+        repo.git(&["init", "-q", "-b", "main"]);
+        repo
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn git_dir(&self) -> PathBuf {
+        self.dir.join(".git")
+    }
+
+    /// Runs git in the repository and returns its trimmed stdout, panicking
+    /// when it fails.
+    pub fn git(&self, args: &[&str]) -> String {
+        let date = format!("@{} +0000", self.clock.get());
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&self.dir)
+            // Keep the user's and the system's config out of the test
+            .env("HOME", &self.dir)
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_AUTHOR_NAME", "Test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "Test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .env("GIT_AUTHOR_DATE", &date)
+            .env("GIT_COMMITTER_DATE", &date)
+            .output()
+            .expect("run git");
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout)
+            .expect("git output is UTF-8")
+            .trim()
+            .to_string()
+    }
+
+    /// Writes `contents` to `file` and commits it.
+    pub fn commit(&self, file: &str, contents: &str) {
+        fs::write(self.dir.join(file), contents).expect("write file");
+        self.clock.set(self.clock.get() + 60);
+        self.git(&["add", file]);
+        self.git(&["commit", "-q", "-m", &format!("Update {}", file)]);
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}